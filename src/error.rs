@@ -16,11 +16,56 @@ pub enum Error {
     InvalidResponse {
         resp: Value,
     },
+    /// Cookies of a logged-in account are required
+    #[snafu(display("Login required: {}", resp))]
+    LoginRequired {
+        resp: Value,
+    },
+    /// The content is paid and has not been purchased by the account
+    #[snafu(display("Purchase required: {}", resp))]
+    PurchaseRequired {
+        resp: Value,
+    },
+    /// The live room is not streaming
+    #[snafu(display("Live room {} is offline", room))]
+    LiveOffline {
+        room: String,
+    },
+    /// The server refuses to serve the request, usually due to risk control
+    #[snafu(display("Request rejected ({}): {}", code, message))]
+    RequestRejected {
+        code: i64,
+        message: String,
+    },
     #[snafu(display("Fails to fetch `{}`: {}", url, source))]
     NetworkError {
         url: Url,
         source: reqwest::Error,
     },
+    #[snafu(display("WebSocket error: {}", source))]
+    WebSocketError {
+        source: Box<tokio_tungstenite::tungstenite::Error>,
+    },
+    #[snafu(display("Invalid packet: {}", reason))]
+    InvalidPacket {
+        reason: String,
+    },
+    /// A live stream extractor returns no video to record
+    #[snafu(display("No video track to record"))]
+    NoVideoTrack,
+    /// No data is received in time
+    #[snafu(display("Timeout fetching `{}`", url))]
+    Timeout {
+        url: Url,
+    },
+    #[snafu(display("Fails to render: {}", reason))]
+    RenderError {
+        reason: String,
+    },
+    #[snafu(context(false))]
+    IoError {
+        source: std::io::Error,
+    },
     #[snafu(context(false))]
     ParseJsonError {
         source: reqwest::Error,
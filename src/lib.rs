@@ -1,10 +1,12 @@
 use once_cell::sync::Lazy;
+use serde_json::Value;
 use std::fmt::Debug;
 use tokio::runtime::Runtime;
 use url::Url;
 use utils::Client;
 
 pub mod error;
+pub mod recorder;
 pub mod utils;
 pub mod website;
 
@@ -12,6 +14,8 @@ pub use crate::error::*;
 pub use Playlist as Finata;
 
 pub type FinaResult<T = Playlist> = Result<T, Error>;
+/// Extra information attached to an `Origin`, such as episode numbers or tags
+pub type Meta = serde_json::Map<String, Value>;
 
 static RUNTIME: Lazy<Runtime> = Lazy::new(|| Runtime::new().unwrap());
 
@@ -47,6 +51,7 @@ pub enum Track {
 pub struct Origin {
     pub tracks: Vec<Track>,
     pub title: String,
+    pub meta: Meta,
 }
 
 #[derive(Debug, PartialEq, Default)]
@@ -76,7 +81,15 @@ impl Debug for Track {
 
 impl Origin {
     pub fn new(tracks: Vec<Track>, title: String) -> Self {
-        Self { tracks, title }
+        Self {
+            tracks,
+            title,
+            meta: Meta::new(),
+        }
+    }
+    pub fn with_meta(mut self, meta: Meta) -> Self {
+        self.meta = meta;
+        self
     }
     pub fn video(url: Url, title: String) -> Self {
         Self::new(vec![Track::Video(url)], title)
@@ -0,0 +1,459 @@
+//! Records live streams (flv or hls) into files.
+//!
+//! A `Recorder` drives a live extractor such as `bilibili::Live` or `weibo::LiveStream`:
+//! the stream url is extracted again whenever the connection drops, stalls or expires,
+//! and recording stops once the extractor reports `Error::LiveOffline`.
+use crate::{error as err, AsClient, Error, Extract, Track};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::{fs::File, io::AsyncWriteExt};
+use url::Url;
+
+const FLV_HEADER_LEN: usize = 9 + 4;
+const FLV_TAG_HEADER_LEN: usize = 11;
+const FLV_TAG_AUDIO: u8 = 8;
+const FLV_TAG_VIDEO: u8 = 9;
+const FLV_TAG_SCRIPT: u8 = 18;
+
+/// When to start a new file. Files are only split at keyframes (flv) or segments (hls)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Split {
+    pub size: Option<u64>,
+    pub duration: Option<Duration>,
+}
+
+pub struct Recorder<E> {
+    extractor: E,
+    dir: PathBuf,
+    prefix: String,
+    split: Split,
+    stall_timeout: Duration,
+    retry_delay: Duration,
+    max_retries: usize,
+}
+
+/// A file being written
+struct Part {
+    file: File,
+    size: u64,
+    duration: Duration,
+}
+
+/// Splits a flv stream into tags
+#[derive(Default)]
+struct FlvParser {
+    buf: Vec<u8>,
+    header: Option<Vec<u8>>,
+    /// tags to put at the beginning of every file: metadata and sequence headers
+    header_tags: [Option<Vec<u8>>; 3],
+}
+
+struct FlvTag {
+    raw: Vec<u8>,
+    kind: u8,
+    timestamp: u32,
+}
+
+struct Segment {
+    seq: u64,
+    url: Url,
+    duration: Duration,
+}
+
+/// A hls media playlist
+struct MediaPlaylist {
+    target_duration: Duration,
+    init: Option<Url>,
+    segments: Vec<Segment>,
+    ended: bool,
+}
+
+impl Split {
+    pub fn size(size: u64) -> Self {
+        Self {
+            size: Some(size),
+            ..Self::default()
+        }
+    }
+    pub fn duration(duration: Duration) -> Self {
+        Self {
+            duration: Some(duration),
+            ..Self::default()
+        }
+    }
+    fn due(&self, part: &Part) -> bool {
+        self.size.is_some_and(|size| part.size >= size)
+            || self.duration.is_some_and(|d| part.duration >= d)
+    }
+}
+
+impl Part {
+    async fn create(path: &Path) -> Result<Self, Error> {
+        Ok(Self {
+            file: File::create(path).await?,
+            size: 0,
+            duration: Duration::ZERO,
+        })
+    }
+    async fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.file.write_all(buf).await?;
+        self.size += buf.len() as u64;
+        Ok(())
+    }
+    /// Flushes the file, so that write errors are not lost when it is dropped
+    async fn finish(mut self) -> Result<(), Error> {
+        self.file.flush().await?;
+        Ok(())
+    }
+}
+
+impl FlvTag {
+    fn data(&self) -> &[u8] {
+        &self.raw[FLV_TAG_HEADER_LEN..self.raw.len() - 4]
+    }
+    /// Index into `FlvParser::header_tags` if this is metadata or a sequence header
+    fn header_slot(&self) -> Option<usize> {
+        let data = self.data();
+        match self.kind {
+            FLV_TAG_SCRIPT => Some(0),
+            // avc (7) or hevc (12) sequence header
+            FLV_TAG_VIDEO if data.len() > 1 && matches!(data[0] & 0x0f, 7 | 12) && data[1] == 0 => {
+                Some(1)
+            }
+            // aac sequence header
+            FLV_TAG_AUDIO if data.len() > 1 && data[0] >> 4 == 10 && data[1] == 0 => Some(2),
+            _ => None,
+        }
+    }
+    fn is_keyframe(&self) -> bool {
+        self.kind == FLV_TAG_VIDEO && self.data().first().is_some_and(|b| b >> 4 == 1)
+    }
+}
+
+impl FlvParser {
+    fn push(&mut self, chunk: &[u8]) -> Result<Vec<FlvTag>, Error> {
+        self.buf.extend_from_slice(chunk);
+        let mut pos = 0;
+        if self.header.is_none() {
+            if self.buf.len() < FLV_HEADER_LEN {
+                return Ok(Vec::new());
+            }
+            if !self.buf.starts_with(b"FLV") {
+                return err::InvalidPacket {
+                    reason: "not a flv stream",
+                }
+                .fail();
+            }
+            self.header = Some(self.buf[..FLV_HEADER_LEN].to_vec());
+            pos = FLV_HEADER_LEN;
+        }
+        let mut tags = Vec::new();
+        while self.buf.len() - pos >= FLV_TAG_HEADER_LEN {
+            let head = &self.buf[pos..];
+            let data_len = u32::from_be_bytes([0, head[1], head[2], head[3]]) as usize;
+            let len = FLV_TAG_HEADER_LEN + data_len + 4;
+            if head.len() < len {
+                break;
+            }
+            let timestamp = u32::from_be_bytes([head[7], head[4], head[5], head[6]]);
+            let tag = FlvTag {
+                raw: head[..len].to_vec(),
+                kind: head[0] & 0x1f,
+                timestamp,
+            };
+            tags.push(tag);
+            pos += len;
+        }
+        self.buf.drain(..pos);
+        Ok(tags)
+    }
+}
+
+impl MediaPlaylist {
+    fn parse(text: &str, base: &Url) -> Result<Self, Error> {
+        if !text.trim_start().starts_with("#EXTM3U") {
+            return err::InvalidPacket {
+                reason: "not a m3u8 playlist",
+            }
+            .fail();
+        }
+        let mut playlist = Self {
+            target_duration: Duration::from_secs(1),
+            init: None,
+            segments: Vec::new(),
+            ended: false,
+        };
+        let mut seq = 0;
+        let mut duration = Duration::ZERO;
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            if let Some(d) = line.strip_prefix("#EXT-X-TARGETDURATION:") {
+                playlist.target_duration = Duration::from_secs(d.parse().unwrap_or(1));
+            } else if let Some(s) = line.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+                seq = s.parse().unwrap_or(0);
+            } else if let Some(d) = line.strip_prefix("#EXTINF:") {
+                let d = d.split(',').next().unwrap_or_default();
+                duration = Duration::from_secs_f64(d.parse().unwrap_or(0.0));
+            } else if let Some(map) = line.strip_prefix("#EXT-X-MAP:") {
+                let uri = map
+                    .split(',')
+                    .find_map(|attr| attr.strip_prefix("URI="))
+                    .map(|uri| uri.trim_matches('"'));
+                if let Some(uri) = uri {
+                    playlist.init = Some(base.join(uri)?);
+                }
+            } else if line.starts_with("#EXT-X-ENDLIST") {
+                playlist.ended = true;
+            } else if !line.starts_with('#') {
+                playlist.segments.push(Segment {
+                    seq,
+                    url: base.join(line)?,
+                    duration,
+                });
+                seq += 1;
+                duration = Duration::ZERO;
+            }
+        }
+        Ok(playlist)
+    }
+}
+
+fn is_hls(url: &Url) -> bool {
+    url.path().ends_with(".m3u8")
+}
+
+impl<E: Extract + AsClient + Send> Recorder<E> {
+    /// Records streams extracted by `extractor` into `dir`
+    pub fn new(extractor: E, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            extractor,
+            dir: dir.into(),
+            prefix: String::from("live"),
+            split: Split::default(),
+            stall_timeout: Duration::from_secs(30),
+            retry_delay: Duration::from_secs(5),
+            max_retries: 10,
+        }
+    }
+    /// Names files `{prefix}_{unix time}_{part}.{ext}`
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+    pub fn split(mut self, split: Split) -> Self {
+        self.split = split;
+        self
+    }
+    /// Reconnects if no data is received within `timeout`
+    pub fn stall_timeout(mut self, timeout: Duration) -> Self {
+        self.stall_timeout = timeout;
+        self
+    }
+    /// Gives up after `max_retries` attempts without receiving media, waiting `delay` between them
+    pub fn retry(mut self, max_retries: usize, delay: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_delay = delay;
+        self
+    }
+    pub fn extractor(&self) -> &E {
+        &self.extractor
+    }
+
+    /// Records until the room goes offline, returning files written
+    pub async fn record(&mut self) -> Result<Vec<PathBuf>, Error> {
+        let mut files = Vec::new();
+        let mut failures = 0;
+        loop {
+            let result = match self.extractor.extract().await {
+                Ok(playlist) => {
+                    let url =
+                        playlist
+                            .raws()
+                            .iter()
+                            .flat_map(|raw| &raw.tracks)
+                            .find_map(|track| match track {
+                                Track::Video(url) => Some(url.clone()),
+                                _ => None,
+                            });
+                    match url {
+                        Some(url) if is_hls(&url) => self.record_hls(&url, &mut files).await,
+                        Some(url) => self.record_flv(&url, &mut files).await,
+                        None => return err::NoVideoTrack.fail(),
+                    }
+                }
+                Err(Error::LiveOffline { .. }) => return Ok(files),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(written) if written > 0 => failures = 0,
+                Err(e @ Error::IoError { .. }) => return Err(e),
+                Err(e) if failures >= self.max_retries => return Err(e),
+                _ if failures >= self.max_retries => return Ok(files),
+                _ => {
+                    failures += 1;
+                    tokio::time::sleep(self.retry_delay).await;
+                }
+            }
+        }
+    }
+
+    async fn new_part(&self, ext: &str, files: &mut Vec<PathBuf>) -> Result<Part, Error> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let path = self.dir.join(format!(
+            "{}_{}_{:03}.{}",
+            self.prefix,
+            now,
+            files.len(),
+            ext
+        ));
+        let part = Part::create(&path).await?;
+        files.push(path);
+        Ok(part)
+    }
+
+    /// Records a flv stream until the connection ends, returning bytes of media tags written
+    async fn record_flv(&self, url: &Url, files: &mut Vec<PathBuf>) -> Result<u64, Error> {
+        let mut resp = self
+            .extractor
+            .client()
+            .get(url.clone())
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|source| Error::NetworkError {
+                url: url.clone(),
+                source,
+            })?;
+        let mut parser = FlvParser::default();
+        let mut part: Option<(Part, u32)> = None;
+        let mut written = 0;
+        loop {
+            let chunk = match tokio::time::timeout(self.stall_timeout, resp.chunk()).await {
+                Ok(Ok(Some(chunk))) => chunk,
+                // stalled or finished
+                Err(_) | Ok(Ok(None)) => break,
+                Ok(Err(_)) if written > 0 => break,
+                Ok(Err(source)) => {
+                    return Err(Error::NetworkError {
+                        url: url.clone(),
+                        source,
+                    })
+                }
+            };
+            for tag in parser.push(&chunk)? {
+                // cached in stream order, so that a new file never repeats a header tag
+                if let Some(slot) = tag.header_slot() {
+                    parser.header_tags[slot] = Some(tag.raw.clone());
+                }
+                let rotate = match part {
+                    None => true,
+                    Some((ref mut p, start)) => {
+                        p.duration =
+                            Duration::from_millis(tag.timestamp.saturating_sub(start) as u64);
+                        tag.is_keyframe() && self.split.due(p)
+                    }
+                };
+                if rotate {
+                    if let Some((p, _)) = part.take() {
+                        p.finish().await?;
+                    }
+                    let mut p = self.new_part("flv", files).await?;
+                    p.write(parser.header.as_deref().unwrap_or_default())
+                        .await?;
+                    for header in parser.header_tags.iter().flatten() {
+                        p.write(header).await?;
+                    }
+                    part = Some((p, tag.timestamp));
+                    // already written as a header tag
+                    if tag.header_slot().is_some() {
+                        continue;
+                    }
+                }
+                if let Some((ref mut p, _)) = part {
+                    p.write(&tag.raw).await?;
+                    // headers alone are sent by stalled streams as well
+                    if tag.header_slot().is_none() {
+                        written += tag.raw.len() as u64;
+                    }
+                }
+            }
+        }
+        if let Some((p, _)) = part {
+            p.finish().await?;
+        }
+        Ok(written)
+    }
+
+    /// Records a hls stream until it ends or stalls, returning bytes of media segments written
+    async fn record_hls(&self, url: &Url, files: &mut Vec<PathBuf>) -> Result<u64, Error> {
+        let client = self.extractor.client();
+        let fetch = |url: Url| async move {
+            let resp = tokio::time::timeout(self.stall_timeout, async {
+                client
+                    .get(url.clone())
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .bytes()
+                    .await
+            })
+            .await;
+            match resp {
+                Ok(Ok(bytes)) => Ok(bytes),
+                Ok(Err(source)) => Err(Error::NetworkError { url, source }),
+                Err(_) => err::Timeout { url }.fail(),
+            }
+        };
+        let mut last_seq = None;
+        let mut last_new = Instant::now();
+        let mut part: Option<Part> = None;
+        let mut written = 0;
+        loop {
+            let playlist = match fetch(url.clone()).await {
+                Ok(text) => MediaPlaylist::parse(&String::from_utf8_lossy(&text), url)?,
+                Err(_) if written > 0 => break,
+                Err(e) => return Err(e),
+            };
+            let ext = if playlist.init.is_some() { "mp4" } else { "ts" };
+            for segment in &playlist.segments {
+                if last_seq.is_some_and(|seq| segment.seq <= seq) {
+                    continue;
+                }
+                let data = match fetch(segment.url.clone()).await {
+                    Ok(data) => data,
+                    Err(_) if written > 0 => break,
+                    Err(e) => return Err(e),
+                };
+                if part.as_ref().is_none_or(|p| self.split.due(p)) {
+                    if let Some(p) = part.take() {
+                        p.finish().await?;
+                    }
+                    let mut p = self.new_part(ext, files).await?;
+                    if let Some(init) = &playlist.init {
+                        p.write(&fetch(init.clone()).await?).await?;
+                    }
+                    part = Some(p);
+                }
+                if let Some(p) = part.as_mut() {
+                    p.write(&data).await?;
+                    p.duration += segment.duration;
+                    written += data.len() as u64;
+                }
+                last_seq = Some(segment.seq);
+                last_new = Instant::now();
+            }
+            if playlist.ended || last_new.elapsed() >= self.stall_timeout {
+                break;
+            }
+            tokio::time::sleep(playlist.target_duration / 2).await;
+        }
+        if let Some(p) = part {
+            p.finish().await?;
+        }
+        Ok(written)
+    }
+}
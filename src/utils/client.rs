@@ -70,6 +70,10 @@ impl Client {
             .json()
             .await?)
     }
+    /// Returns a GET request carrying headers of the client
+    pub fn get(&self, url: Url) -> reqwest::RequestBuilder {
+        self.inner.get(url).headers(self.header.clone())
+    }
     pub fn client(&self) -> &reqwest::Client {
         &self.inner
     }
@@ -92,6 +96,23 @@ impl Client {
             .json::<Value>()
             .await?)
     }
+    /// Posts `body` serialized as json
+    pub async fn post_json_body<T: serde::Serialize>(
+        &self,
+        url: Url,
+        body: &T,
+    ) -> Result<Value, err::Error> {
+        Ok(self
+            .inner
+            .post(url.clone())
+            .headers(self.header.clone())
+            .json(body)
+            .send()
+            .await
+            .context(err::NetworkError { url })?
+            .json::<Value>()
+            .await?)
+    }
     pub async fn post_json(&self, url: Url) -> Result<Value, err::Error> {
         Ok(self
             .inner
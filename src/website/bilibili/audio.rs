@@ -0,0 +1,268 @@
+//! Extractors for the audio area: songs (`/audio/au{sid}`) and music menus (`/audio/am{sid}`)
+use super::{check_code, is_unavailable, unavailable, CONCURRENCY, HEADERS};
+use crate::{error as err, utils::Client, AsClient, Error, Extract, Meta, Origin, Playlist, Track};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use serde_json::Value;
+use url::Url;
+
+/// ?sid={}
+pub const AUDIO_INFO_API: &str = "https://www.bilibili.com/audio/music-service-c/web/song/info";
+/// ?songid={}&quality={}&privilege=2&mid=0&platform=android
+pub const AUDIO_URL_API: &str = "https://api.bilibili.com/audio/music-service-c/url";
+/// ?sid={}
+pub const MENU_INFO_API: &str = "https://www.bilibili.com/audio/music-service-c/web/menu/info";
+/// ?sid={}&pn={}&ps={}
+pub const MENU_SONGS_API: &str = "https://www.bilibili.com/audio/music-service-c/web/song/of-menu";
+const PAGE_SIZE: u64 = 100;
+
+/// Quality of audio streams. Lossless requires a premium account,
+/// and lower qualities are returned if the requested one is unavailable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AudioQuality {
+    K128,
+    K192,
+    #[default]
+    K320,
+    Lossless,
+}
+
+/// A extractor for songs in the audio area
+#[derive(Debug)]
+pub struct Audio {
+    client: Client,
+    sid: u64,
+    quality: AudioQuality,
+}
+
+/// A extractor for music menus (歌单), extracting every song in them
+/// Songs that cannot be played are kept without tracks and marked `unavailable` in the meta
+#[derive(Debug)]
+pub struct AudioMenu {
+    client: Client,
+    sid: u64,
+    quality: AudioQuality,
+    concurrency: usize,
+}
+
+impl AudioQuality {
+    fn as_param(&self) -> u8 {
+        *self as u8
+    }
+}
+
+/// Parses `{prefix}{sid}` in the last segment of `/audio/{prefix}{sid}`
+fn audio_id(url: &Url, prefix: &str) -> Option<u64> {
+    url.path_segments()?
+        .rfind(|p| !p.is_empty())?
+        .strip_prefix(prefix)?
+        .parse()
+        .ok()
+}
+
+/// Builds an `Origin` from `song`, the data of `AUDIO_INFO_API` or an item of `MENU_SONGS_API`
+async fn song_origin(
+    client: &Client,
+    song: &Value,
+    quality: AudioQuality,
+) -> Result<Origin, Error> {
+    let sid = match song["id"].as_u64() {
+        Some(sid) => sid,
+        None => return err::InvalidResponse { resp: song.clone() }.fail(),
+    };
+    let url = Url::parse_with_params(
+        AUDIO_URL_API,
+        [
+            ("songid", sid.to_string()),
+            ("quality", quality.as_param().to_string()),
+            ("privilege", "2".to_owned()),
+            ("mid", "0".to_owned()),
+            ("platform", "android".to_owned()),
+        ],
+    )?;
+    let data = check_code(client.send_json_request(url).await?)?;
+    let mirrors = match data["data"]["cdns"] {
+        Value::Array(ref cdns) => cdns
+            .iter()
+            .filter_map(Value::as_str)
+            .map(Url::parse)
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => Vec::new(),
+    };
+    let mut tracks = match mirrors.first() {
+        Some(url) => vec![Track::Audio(url.clone())],
+        None => return err::InvalidResponse { resp: data }.fail(),
+    };
+    if let Some(lyric) = song["lyric"].as_str().filter(|url| !url.is_empty()) {
+        tracks.push(Track::Text(lyric.parse()?));
+    }
+    if let Some(cover) = song["cover"].as_str().filter(|url| !url.is_empty()) {
+        tracks.push(Track::Image(cover.parse()?));
+    }
+    // the quality actually returned
+    let quality = data["data"]["qualities"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|q| q["type"] == data["data"]["type"])
+        .and_then(|q| q["desc"].as_str())
+        .unwrap_or_default();
+    let mut meta = Meta::new();
+    meta.insert("sid".to_owned(), sid.into());
+    meta.insert("author".to_owned(), song["author"].clone());
+    meta.insert("uploader".to_owned(), song["uname"].clone());
+    meta.insert("duration".to_owned(), song["duration"].clone());
+    meta.insert("quality".to_owned(), quality.into());
+    meta.insert(
+        "mirrors".to_owned(),
+        mirrors.iter().map(|url| url.as_str()).collect(),
+    );
+    let title = song["title"].as_str().unwrap_or_default().to_owned();
+    Ok(Origin::new(tracks, title).with_meta(meta))
+}
+
+impl Audio {
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url = Url::parse(s)?;
+        match audio_id(&url, "au") {
+            Some(sid) => Ok(Self::with_id(sid)),
+            None => Err(Error::InvalidUrl { url }),
+        }
+    }
+    pub fn with_id(sid: u64) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), sid)
+    }
+    pub fn with_client(client: Client, sid: u64) -> Self {
+        Self {
+            client,
+            sid,
+            quality: AudioQuality::default(),
+        }
+    }
+    pub fn quality(mut self, quality: AudioQuality) -> Self {
+        self.quality = quality;
+        self
+    }
+    pub async fn info_json(&self) -> Result<Value, Error> {
+        let url = format!("{}?sid={}", AUDIO_INFO_API, self.sid).parse()?;
+        let data = check_code(self.client.send_json_request(url).await?)?;
+        match data["data"] {
+            Value::Object(_) => Ok(data),
+            _ => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for Audio {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let info = self.info_json().await?;
+        let origin = song_origin(&self.client, &info["data"], self.quality).await?;
+        let title = origin.title.clone();
+        Ok(Playlist::new(vec![origin], title))
+    }
+}
+
+impl AsClient for Audio {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
+
+impl AudioMenu {
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url = Url::parse(s)?;
+        match audio_id(&url, "am") {
+            Some(sid) => Ok(Self::with_id(sid)),
+            None => Err(Error::InvalidUrl { url }),
+        }
+    }
+    pub fn with_id(sid: u64) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), sid)
+    }
+    pub fn with_client(client: Client, sid: u64) -> Self {
+        Self {
+            client,
+            sid,
+            quality: AudioQuality::default(),
+            concurrency: CONCURRENCY,
+        }
+    }
+    pub fn quality(mut self, quality: AudioQuality) -> Self {
+        self.quality = quality;
+        self
+    }
+    /// Number of songs extracted at the same time, 4 by default
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    pub async fn title(&self) -> Result<String, Error> {
+        let url = format!("{}?sid={}", MENU_INFO_API, self.sid).parse()?;
+        let data = check_code(self.client.send_json_request(url).await?)?;
+        match data["data"]["title"] {
+            Value::String(ref title) => Ok(title.to_owned()),
+            _ => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+    /// Returns information of every song in the menu
+    pub async fn songs(&self) -> Result<Vec<Value>, Error> {
+        let mut songs = Vec::new();
+        let mut pn = 1;
+        loop {
+            let url = format!(
+                "{}?sid={}&pn={}&ps={}",
+                MENU_SONGS_API, self.sid, pn, PAGE_SIZE
+            )
+            .parse()?;
+            let data = check_code(self.client.send_json_request(url).await?)?;
+            match data["data"]["data"] {
+                Value::Array(ref page) => songs.extend_from_slice(page),
+                _ => return err::InvalidResponse { resp: data }.fail(),
+            }
+            let page_count = data["data"]["pageCount"].as_u64().unwrap_or(0);
+            if pn >= page_count {
+                return Ok(songs);
+            }
+            pn += 1;
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for AudioMenu {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let songs = self.songs().await?;
+        let extractions = songs.into_iter().map(|song| {
+            let (client, quality) = (self.client.clone(), self.quality);
+            async move {
+                match song_origin(&client, &song, quality).await {
+                    // e.g. songs removed or restricted by copyright
+                    Err(e) if is_unavailable(&e) => {
+                        let title = song["title"].as_str().unwrap_or_default();
+                        let mut origin = unavailable(title.to_owned());
+                        origin.meta.insert("sid".to_owned(), song["id"].clone());
+                        Ok(origin)
+                    }
+                    res => res,
+                }
+            }
+        });
+        let origins = stream::iter(extractions)
+            .buffered(self.concurrency)
+            .try_collect()
+            .await?;
+        Ok(Playlist::new(origins, self.title().await?))
+    }
+}
+
+impl AsClient for AudioMenu {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
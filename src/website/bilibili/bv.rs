@@ -0,0 +1,53 @@
+//! Offline conversion between av numbers and BV ids, compatible with aids larger than 2^30
+
+const XOR_CODE: u64 = 23442827791579;
+const MASK_CODE: u64 = (1 << 51) - 1;
+const MAX_AID: u64 = 1 << 51;
+const BASE: u64 = 58;
+const ALPHABET: &[u8; 58] = b"FcwAPNKTMug3GV5Lj7EJnHpWsx4tb8haYeviqBz6rkCy12mUSDQX9RdoZf";
+const BV_LEN: usize = 12;
+
+fn swap(bv: &mut [u8]) {
+    bv.swap(3, 9);
+    bv.swap(4, 7);
+}
+
+/// Converts an av number into BV id, returning `None` if `aid` is out of range
+pub fn av_to_bv(aid: u64) -> Option<String> {
+    if aid == 0 || aid >= MAX_AID {
+        return None;
+    }
+    let mut bv = *b"BV1000000000";
+    let mut tmp = (MAX_AID | aid) ^ XOR_CODE;
+    let mut idx = BV_LEN - 1;
+    while tmp > 0 {
+        bv[idx] = ALPHABET[(tmp % BASE) as usize];
+        tmp /= BASE;
+        idx -= 1;
+    }
+    swap(&mut bv);
+    Some(String::from_utf8_lossy(&bv).into_owned())
+}
+
+/// Converts a BV id into av number, returning `None` if `bvid` is invalid
+pub fn bv_to_av(bvid: &str) -> Option<u64> {
+    let mut bv: [u8; BV_LEN] = bvid.as_bytes().try_into().ok()?;
+    if !bv.starts_with(b"BV1") {
+        return None;
+    }
+    swap(&mut bv);
+    let mut tmp = 0u64;
+    for c in &bv[3..] {
+        let digit = ALPHABET.iter().position(|a| a == c)? as u64;
+        tmp = tmp.checked_mul(BASE)?.checked_add(digit)?;
+    }
+    match (tmp & MASK_CODE) ^ XOR_CODE {
+        0 => None,
+        aid => Some(aid),
+    }
+}
+
+/// Checks whether `bvid` is a well-formed BV id
+pub fn is_valid_bv(bvid: &str) -> bool {
+    bv_to_av(bvid).and_then(av_to_bv).as_deref() == Some(bvid)
+}
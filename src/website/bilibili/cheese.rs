@@ -0,0 +1,180 @@
+//! Extractor for courses (课堂), which are served by the pugv apis.
+//! Paid episodes are available only when cookies of an account that purchased the course are passed
+use super::{
+    check_code, dash, is_unavailable, parse_dash, parse_durl, unavailable, Id, CONCURRENCY, HEADERS,
+};
+use crate::{error as err, utils::Client, AsClient, Error, Extract, Meta, Origin, Playlist};
+use futures_util::{stream, StreamExt};
+use serde_json::Value;
+use url::Url;
+
+/// ?season_id={} or ?ep_id={}
+pub const COURSE_API: &str = "https://api.bilibili.com/pugv/view/web/season";
+/// ?avid={}&cid={}&ep_id={}&qn={}&fnval={}&fourk=1
+pub const COURSE_VIDEO_API: &str = "https://api.bilibili.com/pugv/player/web/playurl";
+/// status of episodes that can be watched without purchasing, e.g. trial episodes
+const STATUS_PLAYABLE: u64 = 1;
+
+/// A extractor for courses (`/cheese/play/ss{id}` or `/cheese/play/ep{id}`)
+pub struct Course {
+    client: Client,
+    id: Id,
+    course: bool,
+    concurrency: usize,
+}
+
+impl Course {
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url: Url = Url::parse(s)?;
+        match Id::from_url(&url)? {
+            id @ (Id::Ep(_) | Id::Ss(_)) => Ok(Self::with_id(id)),
+            _ => Err(Error::InvalidUrl { url }),
+        }
+    }
+    pub fn with_id(id: Id) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), id)
+    }
+    pub fn with_client(client: Client, id: Id) -> Self {
+        Self {
+            client,
+            id,
+            course: false,
+            concurrency: CONCURRENCY,
+        }
+    }
+    /// Extracts every episode of the course instead of a single one.
+    /// Episodes requiring purchase are kept without tracks and marked `purchase_required`
+    /// in the meta, and `Error::PurchaseRequired` is returned if none of the episodes is available
+    pub fn extracts_course(mut self) -> Self {
+        self.course = true;
+        self
+    }
+    /// Number of episodes extracted at the same time, 4 by default
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    pub async fn course_json(&self) -> Result<Value, Error> {
+        let url = match self.id {
+            Id::Ep(epid) => format!("{}?ep_id={}", COURSE_API, epid),
+            Id::Ss(ssid) => format!("{}?season_id={}", COURSE_API, ssid),
+            _ => unreachable!(),
+        }
+        .parse()?;
+        let data = check_code(self.client.send_json_request(url).await?)?;
+        match data["data"]["episodes"] {
+            Value::Array(_) => Ok(data),
+            _ => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+    /// Extracts an episode of `course`, which is the data of `course_json`
+    async fn episode(&self, course: &Value, ep: &Value) -> Result<Origin, Error> {
+        let purchased = course["user_status"]["payed"].as_u64() == Some(1);
+        if !purchased && ep["status"].as_u64() != Some(STATUS_PLAYABLE) {
+            return err::PurchaseRequired { resp: ep.clone() }.fail();
+        }
+        let (aid, cid, epid) = match (ep["aid"].as_u64(), ep["cid"].as_u64(), ep["id"].as_u64()) {
+            (Some(aid), Some(cid), Some(epid)) => (aid, cid, epid),
+            _ => return err::InvalidResponse { resp: ep.clone() }.fail(),
+        };
+        let url = format!(
+            "{}?avid={}&cid={}&ep_id={}&qn=127&fnval={}&fourk=1",
+            COURSE_VIDEO_API,
+            aid,
+            cid,
+            epid,
+            dash::FNVAL_ALL
+        )
+        .parse()?;
+        let data = check_code(self.client.send_json_request(url).await?)?;
+        let mut origin = match parse_dash(&data["data"]["dash"])
+            .transpose()
+            .or_else(|| parse_durl(&data["data"]["durl"]).transpose())
+        {
+            Some(Ok(origin)) => origin,
+            // no streams are returned for episodes not purchased
+            _ if !purchased => return err::PurchaseRequired { resp: data }.fail(),
+            _ => return err::InvalidResponse { resp: data }.fail(),
+        };
+        origin.title = ep["title"].as_str().unwrap_or_default().to_owned();
+        origin.meta.extend(episode_meta(ep));
+        Ok(origin)
+    }
+}
+
+fn episode_meta(ep: &Value) -> Meta {
+    let mut meta = Meta::new();
+    meta.insert("ep_id".to_owned(), ep["id"].clone());
+    meta.insert("aid".to_owned(), ep["aid"].clone());
+    meta.insert("cid".to_owned(), ep["cid"].clone());
+    meta.insert("index".to_owned(), ep["index"].clone());
+    meta.insert("duration".to_owned(), ep["duration"].clone());
+    meta
+}
+
+#[async_trait::async_trait]
+impl Extract for Course {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let data = self.course_json().await?;
+        let course = &data["data"];
+        let eps = course["episodes"].as_array().cloned().unwrap_or_default();
+        let origins = if self.course {
+            let this = &*self;
+            let extractions = eps.iter().cloned().map(|ep| async move {
+                let result = this.episode(course, &ep).await;
+                (ep, result)
+            });
+            let results = stream::iter(extractions)
+                .buffered(self.concurrency)
+                .collect::<Vec<_>>()
+                .await;
+            let mut origins = Vec::with_capacity(results.len());
+            let mut purchase_required = None;
+            for (ep, result) in results {
+                let mut origin = match result {
+                    Ok(origin) => {
+                        origins.push(origin);
+                        continue;
+                    }
+                    Err(e @ Error::PurchaseRequired { .. }) => {
+                        purchase_required = Some(e);
+                        let mut origin = unavailable(String::new());
+                        origin
+                            .meta
+                            .insert("purchase_required".to_owned(), true.into());
+                        origin
+                    }
+                    Err(e) if is_unavailable(&e) => unavailable(String::new()),
+                    Err(e) => return Err(e),
+                };
+                origin.title = ep["title"].as_str().unwrap_or_default().to_owned();
+                origin.meta.extend(episode_meta(&ep));
+                origins.push(origin);
+            }
+            match purchase_required {
+                Some(e) if origins.iter().all(|origin| origin.tracks.is_empty()) => return Err(e),
+                _ => origins,
+            }
+        } else {
+            let ep = match self.id {
+                Id::Ep(epid) => eps.iter().find(|ep| ep["id"].as_u64() == Some(epid)),
+                _ => eps.first(),
+            };
+            match ep {
+                Some(ep) => vec![self.episode(course, ep).await?],
+                None => return err::InvalidResponse { resp: data }.fail(),
+            }
+        };
+        let title = course["title"].as_str().unwrap_or_default().to_owned();
+        Ok(Playlist::new(origins, title))
+    }
+}
+
+impl AsClient for Course {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
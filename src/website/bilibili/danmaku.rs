@@ -0,0 +1,375 @@
+//! Client for danmaku (live chat) of bilibili live rooms
+use super::{live::Live, wbi::SignWbi, HEADERS};
+use crate::{error as err, utils::Client, Error};
+use futures_util::{stream::BoxStream, SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::{collections::VecDeque, io::Read, time::Duration};
+use tokio_tungstenite::tungstenite::Message;
+use url::Url;
+
+/// ?id={}&type=0, signed with WBI
+pub const DANMU_INFO_API: &str =
+    "https://api.live.bilibili.com/xlive/web-room/v1/index/getDanmuInfo";
+pub const HEADER_LEN: usize = 16;
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+pub const OP_HEARTBEAT: u32 = 2;
+pub const OP_HEARTBEAT_REPLY: u32 = 3;
+pub const OP_MESSAGE: u32 = 5;
+pub const OP_AUTH: u32 = 7;
+pub const OP_AUTH_REPLY: u32 = 8;
+
+/// body is plain json
+pub const PROTOVER_JSON: u16 = 0;
+/// body is a plain integer, used by heartbeat and auth packets
+pub const PROTOVER_INT: u16 = 1;
+/// body is packets compressed with zlib
+pub const PROTOVER_ZLIB: u16 = 2;
+/// body is packets compressed with brotli
+pub const PROTOVER_BROTLI: u16 = 3;
+
+/// A packet of the live broadcast protocol
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    pub protover: u16,
+    pub op: u32,
+    pub seq: u32,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Danmaku {
+        uid: u64,
+        uname: String,
+        text: String,
+    },
+    Gift {
+        uid: u64,
+        uname: String,
+        gift: String,
+        num: u64,
+        /// price of a single gift
+        price: u64,
+    },
+    SuperChat {
+        uid: u64,
+        uname: String,
+        message: String,
+        /// price in CNY
+        price: u64,
+    },
+    Guard {
+        uid: u64,
+        uname: String,
+        /// 1 for 总督, 2 for 提督 and 3 for 舰长
+        level: u64,
+        num: u64,
+        price: u64,
+    },
+    Enter {
+        uid: u64,
+        uname: String,
+    },
+    Like {
+        uid: u64,
+        uname: String,
+    },
+    /// popularity carried by heartbeat replies
+    Popularity(u32),
+    /// messages not parsed into other variants
+    Other {
+        cmd: String,
+        data: Value,
+    },
+}
+
+/// A client of the danmaku of a live room
+#[derive(Debug)]
+pub struct Danmaku {
+    client: Client,
+    room_id: u64,
+    uid: u64,
+}
+
+fn invalid_packet(reason: impl Into<String>) -> Error {
+    err::InvalidPacket {
+        reason: reason.into(),
+    }
+    .build()
+}
+
+fn ws_error(e: tokio_tungstenite::tungstenite::Error) -> Error {
+    Error::WebSocketError {
+        source: Box::new(e),
+    }
+}
+
+impl Packet {
+    pub fn new(protover: u16, op: u32, body: Vec<u8>) -> Self {
+        Self {
+            protover,
+            op,
+            seq: 1,
+            body,
+        }
+    }
+    pub fn heartbeat() -> Self {
+        Self::new(PROTOVER_INT, OP_HEARTBEAT, Vec::new())
+    }
+    pub fn auth(room_id: u64, uid: u64, token: &str) -> Self {
+        let body = json!({
+            "uid": uid,
+            "roomid": room_id,
+            "protover": PROTOVER_BROTLI,
+            "platform": "web",
+            "type": 2,
+            "key": token,
+        });
+        Self::new(PROTOVER_INT, OP_AUTH, body.to_string().into_bytes())
+    }
+    pub fn encode(&self) -> Vec<u8> {
+        let len = HEADER_LEN + self.body.len();
+        let mut buf = Vec::with_capacity(len);
+        buf.extend_from_slice(&(len as u32).to_be_bytes());
+        buf.extend_from_slice(&(HEADER_LEN as u16).to_be_bytes());
+        buf.extend_from_slice(&self.protover.to_be_bytes());
+        buf.extend_from_slice(&self.op.to_be_bytes());
+        buf.extend_from_slice(&self.seq.to_be_bytes());
+        buf.extend_from_slice(&self.body);
+        buf
+    }
+    /// Decodes all packets in a frame, unpacking compressed ones
+    pub fn decode(mut buf: &[u8]) -> Result<Vec<Self>, Error> {
+        let mut packets = Vec::new();
+        while !buf.is_empty() {
+            if buf.len() < HEADER_LEN {
+                return Err(invalid_packet("incomplete header"));
+            }
+            let len = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize;
+            let header_len = u16::from_be_bytes([buf[4], buf[5]]) as usize;
+            if header_len < HEADER_LEN || len < header_len || len > buf.len() {
+                return Err(invalid_packet(format!(
+                    "bad length {} with header length {}",
+                    len, header_len
+                )));
+            }
+            let protover = u16::from_be_bytes([buf[6], buf[7]]);
+            let op = u32::from_be_bytes([buf[8], buf[9], buf[10], buf[11]]);
+            let seq = u32::from_be_bytes([buf[12], buf[13], buf[14], buf[15]]);
+            let body = &buf[header_len..len];
+            match protover {
+                PROTOVER_ZLIB => {
+                    let mut inflated = Vec::new();
+                    flate2::read::ZlibDecoder::new(body)
+                        .read_to_end(&mut inflated)
+                        .map_err(|e| invalid_packet(e.to_string()))?;
+                    packets.extend(Self::decode(&inflated)?);
+                }
+                PROTOVER_BROTLI => {
+                    let mut inflated = Vec::new();
+                    brotli::Decompressor::new(body, 4096)
+                        .read_to_end(&mut inflated)
+                        .map_err(|e| invalid_packet(e.to_string()))?;
+                    packets.extend(Self::decode(&inflated)?);
+                }
+                _ => packets.push(Self {
+                    protover,
+                    op,
+                    seq,
+                    body: body.to_vec(),
+                }),
+            }
+            buf = &buf[len..];
+        }
+        Ok(packets)
+    }
+}
+
+impl Event {
+    /// Parses the event carried by a packet, returning `None` for packets without events
+    pub fn from_packet(packet: &Packet) -> Option<Result<Self, Error>> {
+        match packet.op {
+            OP_HEARTBEAT_REPLY => Some(match packet.body.get(..4) {
+                Some(&[a, b, c, d]) => Ok(Self::Popularity(u32::from_be_bytes([a, b, c, d]))),
+                _ => Err(invalid_packet("heartbeat reply without popularity")),
+            }),
+            OP_MESSAGE => Some(
+                serde_json::from_slice(&packet.body)
+                    .map(|msg| Self::from_json(&msg))
+                    .map_err(|e| invalid_packet(e.to_string())),
+            ),
+            _ => None,
+        }
+    }
+    /// Parses a message whose `cmd` may carry suffixes like `DANMU_MSG:4:0:2:2:2:0`
+    pub fn from_json(msg: &Value) -> Self {
+        let cmd = msg["cmd"].as_str().unwrap_or_default();
+        let data = &msg["data"];
+        let uid = data["uid"].as_u64().unwrap_or_default();
+        let str_of = |v: &Value| v.as_str().unwrap_or_default().to_owned();
+        let num_of = |v: &Value| {
+            v.as_u64()
+                .or_else(|| v.as_str().and_then(|s| s.parse().ok()))
+                .unwrap_or_default()
+        };
+        match cmd.split(':').next().unwrap_or_default() {
+            "DANMU_MSG" => Self::Danmaku {
+                uid: num_of(&msg["info"][2][0]),
+                uname: str_of(&msg["info"][2][1]),
+                text: str_of(&msg["info"][1]),
+            },
+            "SEND_GIFT" => Self::Gift {
+                uid,
+                uname: str_of(&data["uname"]),
+                gift: str_of(&data["giftName"]),
+                num: num_of(&data["num"]),
+                price: num_of(&data["price"]),
+            },
+            "SUPER_CHAT_MESSAGE" => Self::SuperChat {
+                uid: num_of(&data["uid"]),
+                uname: str_of(&data["user_info"]["uname"]),
+                message: str_of(&data["message"]),
+                price: num_of(&data["price"]),
+            },
+            "GUARD_BUY" => Self::Guard {
+                uid,
+                uname: str_of(&data["username"]),
+                level: num_of(&data["guard_level"]),
+                num: num_of(&data["num"]),
+                price: num_of(&data["price"]),
+            },
+            // msg_type 1 is entering, others are following or sharing
+            "INTERACT_WORD" if data["msg_type"].as_u64() == Some(1) => Self::Enter {
+                uid,
+                uname: str_of(&data["uname"]),
+            },
+            "LIKE_INFO_V3_CLICK" => Self::Like {
+                uid,
+                uname: str_of(&data["uname"]),
+            },
+            _ => Self::Other {
+                cmd: cmd.to_owned(),
+                data: msg.clone(),
+            },
+        }
+    }
+}
+
+impl Danmaku {
+    /// `room_id` may be a short one
+    pub fn new(room_id: u64) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), room_id)
+    }
+    pub fn with_client(client: Client, room_id: u64) -> Self {
+        Self {
+            client,
+            room_id,
+            uid: 0,
+        }
+    }
+    /// Sets uid of the logged-in account whose cookies are passed,
+    /// otherwise user names in danmaku are masked by the server
+    pub fn uid(mut self, uid: u64) -> Self {
+        self.uid = uid;
+        self
+    }
+    /// Returns real room id, websocket url and token used for auth
+    pub async fn danmu_info(&self) -> Result<(u64, Url, String), Error> {
+        let (room_id, _, _) = Live::with_client(self.client.clone(), self.room_id)
+            .room_info()
+            .await?;
+        let url = Url::parse_with_params(
+            DANMU_INFO_API,
+            [("id", room_id.to_string()), ("type", "0".to_owned())],
+        )?;
+        let data = self.client.send_signed_json(&url).await?;
+        let host = &data["data"]["host_list"][0];
+        match (
+            host["host"].as_str(),
+            host["wss_port"].as_u64(),
+            data["data"]["token"].as_str(),
+        ) {
+            (Some(host), Some(port), Some(token)) => {
+                let url = format!("wss://{}:{}/sub", host, port).parse()?;
+                Ok((room_id, url, token.to_owned()))
+            }
+            _ => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+    pub async fn connect(&self) -> Result<BoxStream<'static, Result<Event, Error>>, Error> {
+        let (room_id, url, token) = self.danmu_info().await?;
+        connect(&url, room_id, self.uid, &token).await
+    }
+}
+
+/// Connects to the danmaku server at `url`, sending heartbeats while the stream is polled
+pub async fn connect(
+    url: &Url,
+    room_id: u64,
+    uid: u64,
+    token: &str,
+) -> Result<BoxStream<'static, Result<Event, Error>>, Error> {
+    let (mut ws, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .map_err(ws_error)?;
+    ws.send(Message::Binary(Packet::auth(room_id, uid, token).encode()))
+        .await
+        .map_err(ws_error)?;
+    let mut pending = VecDeque::new();
+    // wait for auth reply, keeping packets that arrive early
+    'auth: loop {
+        let buf = match ws.next().await {
+            Some(Ok(Message::Binary(buf))) => buf,
+            Some(Ok(Message::Close(_))) | None => {
+                return Err(invalid_packet("connection closed before auth reply"))
+            }
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => return Err(ws_error(e)),
+        };
+        for packet in Packet::decode(&buf)? {
+            if packet.op == OP_AUTH_REPLY {
+                let reply: Value = serde_json::from_slice(&packet.body)
+                    .map_err(|e| invalid_packet(e.to_string()))?;
+                match reply["code"].as_i64() {
+                    Some(0) => break 'auth,
+                    code => {
+                        return err::RequestRejected {
+                            code: code.unwrap_or_default(),
+                            message: reply.to_string(),
+                        }
+                        .fail()
+                    }
+                }
+            }
+            pending.extend(Event::from_packet(&packet));
+        }
+    }
+    let heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+    let state = Some((ws, heartbeat, pending));
+    let events = futures_util::stream::unfold(state, |state| async move {
+        let (mut ws, mut heartbeat, mut pending) = state?;
+        loop {
+            if let Some(event) = pending.pop_front() {
+                return Some((event, Some((ws, heartbeat, pending))));
+            }
+            tokio::select! {
+                _ = heartbeat.tick() => {
+                    if let Err(e) = ws.send(Message::Binary(Packet::heartbeat().encode())).await {
+                        return Some((Err(ws_error(e)), None));
+                    }
+                }
+                msg = ws.next() => match msg {
+                    Some(Ok(Message::Binary(buf))) => match Packet::decode(&buf) {
+                        Ok(packets) => pending.extend(packets.iter().filter_map(Event::from_packet)),
+                        Err(e) => pending.push_back(Err(e)),
+                    },
+                    Some(Ok(Message::Close(_))) | None => return None,
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Some((Err(ws_error(e)), None)),
+                },
+            }
+        }
+    });
+    Ok(events.boxed())
+}
@@ -0,0 +1,184 @@
+//! Dash streams returned by the playurl api, labeled with their qualities
+use crate::{Error, Track};
+use serde_json::{json, Value};
+use std::cmp::Reverse;
+
+pub const FNVAL_DASH: u64 = 16;
+pub const FNVAL_HDR: u64 = 64;
+pub const FNVAL_4K: u64 = 128;
+pub const FNVAL_DOLBY_AUDIO: u64 = 256;
+pub const FNVAL_DOLBY_VISION: u64 = 512;
+pub const FNVAL_8K: u64 = 1024;
+pub const FNVAL_AV1: u64 = 2048;
+/// Requests every stream available, hi-res flac is included in dash if the account is allowed to
+pub const FNVAL_ALL: u64 = FNVAL_DASH
+    | FNVAL_HDR
+    | FNVAL_4K
+    | FNVAL_DOLBY_AUDIO
+    | FNVAL_DOLBY_VISION
+    | FNVAL_8K
+    | FNVAL_AV1;
+
+const QN_HDR: u64 = 125;
+const QN_DOLBY_VISION: u64 = 126;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DynamicRange {
+    Sdr,
+    Hdr,
+    DolbyVision,
+}
+
+/// Ordered from worst to best
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AudioFormat {
+    Aac,
+    Dolby,
+    HiResFlac,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Video(DynamicRange),
+    Audio(AudioFormat),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stream {
+    pub track: Track,
+    /// qn for videos, 30216, 30232 or 30280 for aac, 30250 for dolby and 30251 for flac
+    pub id: u64,
+    pub variant: Variant,
+    pub codecs: String,
+    pub bandwidth: u64,
+}
+
+impl DynamicRange {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sdr => "sdr",
+            Self::Hdr => "hdr",
+            Self::DolbyVision => "dolby_vision",
+        }
+    }
+}
+
+impl AudioFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Aac => "aac",
+            Self::Dolby => "dolby",
+            Self::HiResFlac => "flac",
+        }
+    }
+}
+
+impl Stream {
+    fn new(item: &Value, variant: Variant) -> Result<Option<Self>, Error> {
+        let url = match item["baseUrl"]
+            .as_str()
+            .or_else(|| item["base_url"].as_str())
+        {
+            Some(url) => url.parse()?,
+            None => return Ok(None),
+        };
+        let track = match variant {
+            Variant::Video(_) => Track::Video(url),
+            Variant::Audio(_) => Track::Audio(url),
+        };
+        Ok(Some(Self {
+            track,
+            id: item["id"].as_u64().unwrap_or_default(),
+            variant,
+            codecs: item["codecs"].as_str().unwrap_or_default().to_owned(),
+            bandwidth: item["bandwidth"].as_u64().unwrap_or_default(),
+        }))
+    }
+    fn video(item: &Value) -> Result<Option<Self>, Error> {
+        let range = match item["id"].as_u64() {
+            Some(QN_HDR) => DynamicRange::Hdr,
+            Some(QN_DOLBY_VISION) => DynamicRange::DolbyVision,
+            _ => DynamicRange::Sdr,
+        };
+        Self::new(item, Variant::Video(range))
+    }
+    pub fn is_video(&self) -> bool {
+        matches!(self.variant, Variant::Video(_))
+    }
+    pub fn is_audio(&self) -> bool {
+        matches!(self.variant, Variant::Audio(_))
+    }
+    /// Human readable quality, such as "1080P60", "HDR" or "Hi-Res FLAC"
+    pub fn label(&self) -> String {
+        match (self.variant, self.id) {
+            (Variant::Video(_), 127) => "8K".to_owned(),
+            (Variant::Video(_), QN_DOLBY_VISION) => "Dolby Vision".to_owned(),
+            (Variant::Video(_), QN_HDR) => "HDR".to_owned(),
+            (Variant::Video(_), 120) => "4K".to_owned(),
+            (Variant::Video(_), 116) => "1080P60".to_owned(),
+            (Variant::Video(_), 112) => "1080P+".to_owned(),
+            (Variant::Video(_), 80) => "1080P".to_owned(),
+            (Variant::Video(_), 74) => "720P60".to_owned(),
+            (Variant::Video(_), 64) => "720P".to_owned(),
+            (Variant::Video(_), 32) => "480P".to_owned(),
+            (Variant::Video(_), 16) => "360P".to_owned(),
+            (Variant::Video(_), 6) => "240P".to_owned(),
+            (Variant::Audio(AudioFormat::HiResFlac), _) => "Hi-Res FLAC".to_owned(),
+            (Variant::Audio(AudioFormat::Dolby), _) => "Dolby Atmos".to_owned(),
+            (Variant::Audio(AudioFormat::Aac), 30280) => "192K".to_owned(),
+            (Variant::Audio(AudioFormat::Aac), 30232) => "132K".to_owned(),
+            (Variant::Audio(AudioFormat::Aac), 30216) => "64K".to_owned(),
+            (_, id) => id.to_string(),
+        }
+    }
+    /// Videos rank above audios, and better streams of the same kind have larger ranks
+    fn rank(&self) -> (bool, Option<AudioFormat>, u64) {
+        match self.variant {
+            Variant::Video(_) => (true, None, self.id),
+            Variant::Audio(format) => (false, Some(format), self.id),
+        }
+    }
+    pub fn to_json(&self) -> Value {
+        let (kind, variant) = match self.variant {
+            Variant::Video(range) => ("video", range.as_str()),
+            Variant::Audio(format) => ("audio", format.as_str()),
+        };
+        json!({
+            "kind": kind,
+            "url": self.track.as_url().as_str(),
+            "id": self.id,
+            "label": self.label(),
+            "variant": variant,
+            "codecs": self.codecs,
+            "bandwidth": self.bandwidth,
+        })
+    }
+}
+
+/// Lists the streams in `dash`, videos before audios and the best ones first.
+/// Streams of the same quality keep the order of the response
+pub fn streams(dash: &Value) -> Result<Vec<Stream>, Error> {
+    let items = |value: &Value| value.as_array().cloned().unwrap_or_default();
+    let mut streams = Vec::new();
+    for item in items(&dash["video"]) {
+        streams.extend(Stream::video(&item)?);
+    }
+    let audios = items(&dash["audio"])
+        .into_iter()
+        .map(|item| (item, AudioFormat::Aac))
+        .chain(
+            items(&dash["dolby"]["audio"])
+                .into_iter()
+                .map(|item| (item, AudioFormat::Dolby)),
+        )
+        .chain(
+            Some(dash["flac"]["audio"].clone())
+                .filter(Value::is_object)
+                .map(|item| (item, AudioFormat::HiResFlac)),
+        );
+    for (item, format) in audios {
+        streams.extend(Stream::new(&item, Variant::Audio(format))?);
+    }
+    streams.sort_by_key(|stream| Reverse(stream.rank()));
+    Ok(streams)
+}
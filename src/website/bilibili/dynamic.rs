@@ -0,0 +1,270 @@
+//! Extractors for images in dynamics (动态, including opus) and articles (专栏)
+use super::{wbi::SignWbi, Id, Video, HEADERS};
+use crate::{error as err, utils::Client, AsClient, Error, Extract, Origin, Playlist, Track};
+use serde_json::Value;
+use url::Url;
+
+/// ?id={}&features=itemOpusStyle, requires wbi signature
+pub const DYNAMIC_API: &str = "https://api.bilibili.com/x/polymer/web-dynamic/v1/detail";
+/// ?id={}, requires wbi signature
+pub const ARTICLE_API: &str = "https://api.bilibili.com/x/article/view";
+
+/// A extractor for dynamics (`t.bilibili.com/{id}` or `bilibili.com/opus/{id}`).
+/// Forwarded dynamics are resolved to the original ones
+#[derive(Debug)]
+pub struct Dynamic {
+    client: Client,
+    id: u64,
+}
+
+/// A extractor for articles (`bilibili.com/read/cv{id}`)
+#[derive(Debug)]
+pub struct Article {
+    client: Client,
+    cvid: u64,
+}
+
+/// What a dynamic carries
+#[derive(Debug, Clone, PartialEq)]
+pub enum Content {
+    /// text and images in post order, images may be empty for text-only dynamics
+    Images {
+        text: String,
+        images: Vec<Url>,
+    },
+    Video(Id),
+    /// id of the article
+    Article(u64),
+}
+
+/// Turns urls of thumbnails into urls of original images
+/// by removing parameters such as `@1e_1c.webp`
+pub fn original_image(src: &str) -> Result<Url, Error> {
+    let src = src.split('@').next().unwrap_or_default();
+    let src = match src.strip_prefix("//") {
+        Some(src) => format!("https://{}", src),
+        None => src.replacen("http://", "https://", 1),
+    };
+    Ok(src.parse()?)
+}
+
+impl Content {
+    /// Parses `data.item` of `DYNAMIC_API`
+    pub fn from_item(item: &Value) -> Result<Self, Error> {
+        let dynamic = &item["modules"]["module_dynamic"];
+        let major = &dynamic["major"];
+        match item["type"].as_str() {
+            Some("DYNAMIC_TYPE_FORWARD") => return Self::from_item(&item["orig"]),
+            Some("DYNAMIC_TYPE_AV") => {
+                let archive = &major["archive"];
+                let id = archive["bvid"].as_str().and_then(Id::new).or_else(|| {
+                    archive["aid"]
+                        .as_str()
+                        .and_then(|aid| aid.parse().ok())
+                        .or_else(|| archive["aid"].as_u64())
+                        .map(Id::Av)
+                });
+                return match id {
+                    Some(id) => Ok(Self::Video(id)),
+                    None => err::InvalidResponse { resp: item.clone() }.fail(),
+                };
+            }
+            Some("DYNAMIC_TYPE_ARTICLE") => {
+                let cvid = major["article"]["id"].as_u64().or_else(|| {
+                    item["basic"]["rid_str"]
+                        .as_str()
+                        .and_then(|rid| rid.parse().ok())
+                });
+                return match cvid {
+                    Some(cvid) => Ok(Self::Article(cvid)),
+                    None => err::InvalidResponse { resp: item.clone() }.fail(),
+                };
+            }
+            Some(_) => {}
+            None => return err::InvalidResponse { resp: item.clone() }.fail(),
+        }
+        // opus style puts both text and images in `major.opus`
+        let srcs = major["opus"]["pics"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|pic| pic["url"].as_str())
+            .chain(
+                major["draw"]["items"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|pic| pic["src"].as_str()),
+            );
+        let images = srcs.map(original_image).collect::<Result<_, _>>()?;
+        let text = major["opus"]["summary"]["text"]
+            .as_str()
+            .or_else(|| dynamic["desc"]["text"].as_str())
+            .filter(|text| !text.is_empty())
+            .or_else(|| major["opus"]["title"].as_str())
+            .unwrap_or_default()
+            .to_owned();
+        Ok(Self::Images { text, images })
+    }
+}
+
+/// Collects images of `data` from `ARTICLE_API` in post order
+pub fn article_images(data: &Value) -> Result<Vec<Url>, Error> {
+    // articles written in the opus editor
+    if let Value::Array(ref paragraphs) = data["opus"]["content"]["paragraphs"] {
+        return paragraphs
+            .iter()
+            .flat_map(|p| p["pic"]["pics"].as_array().into_iter().flatten())
+            .filter_map(|pic| pic["url"].as_str())
+            .map(original_image)
+            .collect();
+    }
+    let content = data["content"].as_str().unwrap_or_default();
+    content
+        .split("<img")
+        .skip(1)
+        .filter_map(|tag| {
+            let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+            attribute(tag, "data-src").or_else(|| attribute(tag, "src"))
+        })
+        .map(original_image)
+        .collect()
+}
+
+/// Finds value of `name="..."` in a html tag
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let pattern = format!(" {}=\"", name);
+    let start = tag.find(&pattern)? + pattern.len();
+    let len = tag[start..].find('"')?;
+    Some(&tag[start..start + len])
+}
+
+impl Dynamic {
+    /// Accepts `t.bilibili.com/{id}`, `bilibili.com/opus/{id}` and `m.bilibili.com/dynamic/{id}`
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url = Url::parse(s)?;
+        let segments = url
+            .path_segments()
+            .map(|it| it.filter(|p| !p.is_empty()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let id = match (url.host_str(), &segments[..]) {
+            (Some("t.bilibili.com"), [id])
+            | (Some("bilibili.com" | "www.bilibili.com" | "m.bilibili.com"), ["opus", id])
+            | (Some("m.bilibili.com"), ["dynamic", id]) => id.parse().ok(),
+            _ => None,
+        };
+        match id {
+            Some(id) => Ok(Self::with_id(id)),
+            None => Err(Error::InvalidUrl { url }),
+        }
+    }
+    pub fn with_id(id: u64) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), id)
+    }
+    pub fn with_client(client: Client, id: u64) -> Self {
+        Self { client, id }
+    }
+    pub async fn item_json(&self) -> Result<Value, Error> {
+        let url = Url::parse_with_params(
+            DYNAMIC_API,
+            [
+                ("id", self.id.to_string()),
+                ("features", "itemOpusStyle".to_owned()),
+            ],
+        )?;
+        let data = self.client.send_signed_json(&url).await?;
+        match data["data"]["item"] {
+            Value::Object(_) => Ok(data),
+            _ => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+    pub async fn content(&self) -> Result<Content, Error> {
+        let data = self.item_json().await?;
+        Content::from_item(&data["data"]["item"])
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for Dynamic {
+    async fn extract(&mut self) -> crate::FinaResult {
+        match self.content().await? {
+            Content::Images { text, images } => {
+                let tracks = images.into_iter().map(Track::Image).collect();
+                Ok(Playlist::new(vec![Origin::new(tracks, text.clone())], text))
+            }
+            Content::Video(id) => {
+                Video::with_client(self.client.clone(), id, None)
+                    .extract()
+                    .await
+            }
+            Content::Article(cvid) => {
+                Article::with_client(self.client.clone(), cvid)
+                    .extract()
+                    .await
+            }
+        }
+    }
+}
+
+impl AsClient for Dynamic {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
+
+impl Article {
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url = Url::parse(s)?;
+        url.path_segments()
+            .and_then(|mut it| it.rfind(|p| !p.is_empty()))
+            .and_then(|p| p.strip_prefix("cv"))
+            .and_then(|cvid| cvid.parse().ok())
+            .map(Self::with_id)
+            .ok_or(Error::InvalidUrl { url })
+    }
+    pub fn with_id(cvid: u64) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), cvid)
+    }
+    pub fn with_client(client: Client, cvid: u64) -> Self {
+        Self { client, cvid }
+    }
+    pub async fn article_json(&self) -> Result<Value, Error> {
+        let url = format!("{}?id={}", ARTICLE_API, self.cvid).parse()?;
+        let data = self.client.send_signed_json(&url).await?;
+        match data["data"] {
+            Value::Object(_) => Ok(data),
+            _ => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for Article {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let data = self.article_json().await?;
+        let tracks = article_images(&data["data"])?
+            .into_iter()
+            .map(Track::Image)
+            .collect();
+        let title = data["data"]["title"]
+            .as_str()
+            .unwrap_or_default()
+            .to_owned();
+        Ok(Playlist::new(
+            vec![Origin::new(tracks, title.clone())],
+            title,
+        ))
+    }
+}
+
+impl AsClient for Article {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
@@ -0,0 +1,288 @@
+//! Extractor for interactive videos (互动视频), whose segments form a graph of choices
+use super::{check_code, wbi::SignWbi, BaseExtractor, Id, Video, HEADERS};
+use crate::{error as err, utils::Client, AsClient, Error, Extract, Origin, Playlist};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use url::Url;
+
+/// ?aid={}&cid={}, requires wbi signature
+pub const PLAYER_API: &str = "https://api.bilibili.com/x/player/wbi/v2";
+/// ?aid={}&graph_version={}&edge_id={}, the root node is returned without `edge_id`
+pub const EDGE_INFO_API: &str = "https://api.bilibili.com/x/stein/edgeinfo_v2";
+/// stops walking graphs larger than this, see `Graph::truncated`
+const MAX_NODES: usize = 2000;
+
+/// The story of an interactive video. Nodes are keyed by their edge ids
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Graph {
+    pub aid: u64,
+    pub graph_version: u64,
+    pub root: u64,
+    pub nodes: BTreeMap<u64, Node>,
+    /// hidden variables used in conditions, e.g. `$v1 >= 2`
+    pub variables: Vec<Variable>,
+    /// whether nodes are left out as the graph is too large to walk
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+/// A segment of the video and the questions asked at its end
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Node {
+    pub edge_id: u64,
+    pub cid: u64,
+    pub title: String,
+    pub questions: Vec<Question>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Question {
+    pub id: u64,
+    pub title: String,
+    /// 0 for jumping to the default choice directly, 2 for choosing by clicking
+    pub kind: u64,
+    /// milliseconds to choose, -1 for no limit
+    pub duration: i64,
+    pub choices: Vec<Choice>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Choice {
+    /// edge id of the node the choice leads to
+    pub edge_id: u64,
+    pub cid: u64,
+    pub option: String,
+    /// empty if the choice is always available
+    pub condition: String,
+    /// changes to variables, e.g. `$v1=$v1+1`
+    pub action: String,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Variable {
+    /// name used in conditions and actions, such as `$v1`
+    pub id: String,
+    pub name: String,
+    pub value: f64,
+    pub is_shown: bool,
+}
+
+/// A extractor for interactive videos, extracting every segment in the graph.
+/// Graphs too large are extracted in part, which `extract_graph` reports by `Graph::truncated`
+pub struct Interactive {
+    client: Client,
+    id: Id,
+}
+
+impl Choice {
+    fn from_json(choice: &Value) -> Option<Self> {
+        Some(Self {
+            edge_id: choice["id"].as_u64()?,
+            cid: choice["cid"].as_u64()?,
+            option: choice["option"].as_str().unwrap_or_default().to_owned(),
+            condition: choice["condition"].as_str().unwrap_or_default().to_owned(),
+            action: choice["native_action"]
+                .as_str()
+                .unwrap_or_default()
+                .to_owned(),
+            is_default: choice["is_default"].as_u64() == Some(1),
+        })
+    }
+}
+
+impl Node {
+    /// Parses `data` from `EDGE_INFO_API`, `cid` being the segment the node plays
+    pub fn from_json(cid: u64, data: &Value) -> Result<Self, Error> {
+        let edge_id = match data["edge_id"].as_u64() {
+            Some(edge_id) => edge_id,
+            None => return err::InvalidResponse { resp: data.clone() }.fail(),
+        };
+        let questions = data["edges"]["questions"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|question| Question {
+                id: question["id"].as_u64().unwrap_or_default(),
+                title: question["title"].as_str().unwrap_or_default().to_owned(),
+                kind: question["type"].as_u64().unwrap_or_default(),
+                duration: question["duration"].as_i64().unwrap_or(-1),
+                choices: question["choices"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Choice::from_json)
+                    .collect(),
+            })
+            .collect();
+        Ok(Self {
+            edge_id,
+            cid,
+            title: data["title"].as_str().unwrap_or_default().to_owned(),
+            questions,
+        })
+    }
+    pub fn choices(&self) -> impl Iterator<Item = &Choice> {
+        self.questions.iter().flat_map(|q| q.choices.iter())
+    }
+    pub fn is_leaf(&self) -> bool {
+        self.choices().next().is_none()
+    }
+}
+
+impl Variable {
+    fn from_json(var: &Value) -> Option<Self> {
+        Some(Self {
+            id: var["id_v2"].as_str()?.to_owned(),
+            name: var["name"].as_str().unwrap_or_default().to_owned(),
+            value: var["value"].as_f64().unwrap_or_default(),
+            is_shown: var["is_show"].as_u64() == Some(1),
+        })
+    }
+}
+
+impl Interactive {
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url = Url::parse(s)?;
+        match Id::from_url(&url)? {
+            id @ (Id::Av(_) | Id::Bv(_)) => Ok(Self::with_id(id)),
+            _ => Err(Error::InvalidUrl { url }),
+        }
+    }
+    pub fn with_id(id: Id) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), id)
+    }
+    pub fn with_client(client: Client, id: Id) -> Self {
+        Self { client, id }
+    }
+    /// Returns aid, cid of the first segment and title of the video
+    async fn video_info(&self) -> Result<(u64, u64, String), Error> {
+        let video = Video::with_client(self.client.clone(), self.id.clone(), None);
+        let info = check_code(video.video_info_json().await?)?;
+        match (info["data"]["aid"].as_u64(), info["data"]["cid"].as_u64()) {
+            (Some(aid), Some(cid)) => {
+                let title = info["data"]["title"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_owned();
+                Ok((aid, cid, title))
+            }
+            _ => err::InvalidResponse { resp: info }.fail(),
+        }
+    }
+    async fn graph_version(&self, aid: u64, cid: u64) -> Result<u64, Error> {
+        let url = format!("{}?aid={}&cid={}", PLAYER_API, aid, cid).parse()?;
+        let data = self.client.send_signed_json(&url).await?;
+        match data["data"]["interaction"]["graph_version"].as_u64() {
+            Some(version) => Ok(version),
+            // not an interactive video
+            None => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+    async fn edge_info(
+        &self,
+        aid: u64,
+        version: u64,
+        edge_id: Option<u64>,
+    ) -> Result<Value, Error> {
+        let mut url = format!("{}?aid={}&graph_version={}", EDGE_INFO_API, aid, version);
+        if let Some(edge_id) = edge_id {
+            url.push_str(&format!("&edge_id={}", edge_id));
+        }
+        let data = check_code(self.client.send_json_request(url.parse()?).await?)?;
+        match data["data"] {
+            Value::Object(_) => Ok(data),
+            _ => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+    /// Walks every node reachable from the root, ignoring conditions of choices
+    pub async fn graph(&self) -> Result<Graph, Error> {
+        let (aid, cid, _) = self.video_info().await?;
+        self.walk(aid, cid).await
+    }
+    async fn walk(&self, aid: u64, cid: u64) -> Result<Graph, Error> {
+        let graph_version = self.graph_version(aid, cid).await?;
+        let data = self.edge_info(aid, graph_version, None).await?;
+        let root = Node::from_json(cid, &data["data"])?;
+        let variables = data["data"]["hidden_vars"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Variable::from_json)
+            .collect();
+        let mut graph = Graph {
+            aid,
+            graph_version,
+            root: root.edge_id,
+            nodes: BTreeMap::new(),
+            variables,
+            truncated: false,
+        };
+        let mut queue = VecDeque::from([root]);
+        while let Some(node) = queue.pop_front() {
+            for choice in node.choices() {
+                let known = graph.nodes.contains_key(&choice.edge_id)
+                    || queue.iter().any(|n| n.edge_id == choice.edge_id)
+                    || choice.edge_id == node.edge_id;
+                if known {
+                    continue;
+                }
+                if graph.nodes.len() + queue.len() >= MAX_NODES {
+                    graph.truncated = true;
+                    continue;
+                }
+                let data = self
+                    .edge_info(aid, graph_version, Some(choice.edge_id))
+                    .await?;
+                queue.push_back(Node::from_json(choice.cid, &data["data"])?);
+            }
+            graph.nodes.insert(node.edge_id, node);
+        }
+        Ok(graph)
+    }
+    /// Extracts every segment of the video, and returns the graph along
+    pub async fn extract_graph(&self) -> Result<(Playlist, Graph), Error> {
+        let (aid, cid, title) = self.video_info().await?;
+        let graph = self.walk(aid, cid).await?;
+        let mut origins: Vec<Origin> = Vec::new();
+        for node in graph.nodes.values() {
+            // several edges may play the same segment
+            if let Some(origin) = origins
+                .iter_mut()
+                .find(|o| o.meta["cid"].as_u64() == Some(node.cid))
+            {
+                if let Some(Value::Array(edges)) = origin.meta.get_mut("edge_ids") {
+                    edges.push(node.edge_id.into());
+                }
+                continue;
+            }
+            let mut origin = BaseExtractor::new(aid, node.cid, self.client.clone())
+                .origin()
+                .await?;
+            origin.title = node.title.clone();
+            origin.meta.insert("cid".to_owned(), node.cid.into());
+            origin
+                .meta
+                .insert("edge_ids".to_owned(), vec![node.edge_id].into());
+            origins.push(origin);
+        }
+        Ok((Playlist::new(origins, title), graph))
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for Interactive {
+    async fn extract(&mut self) -> crate::FinaResult {
+        Ok(self.extract_graph().await?.0)
+    }
+}
+
+impl AsClient for Interactive {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
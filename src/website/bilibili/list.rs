@@ -0,0 +1,390 @@
+//! Extractors for video lists: favorite folders, watch later, ugc seasons (合集) and series
+use super::{
+    check_code,
+    space::{extract_uploads, Upload},
+    HEADERS,
+};
+use crate::{error as err, utils::Client, AsClient, Error, Extract, Playlist};
+use serde_json::Value;
+use url::Url;
+
+/// ?media_id={}&pn={}&ps={}&platform=web
+pub const FAV_API: &str = "https://api.bilibili.com/x/v3/fav/resource/list";
+/// requires login
+pub const WATCH_LATER_API: &str = "https://api.bilibili.com/x/v2/history/toview";
+/// ?mid={}&season_id={}&page_num={}&page_size={}
+pub const SEASON_ARCHIVES_API: &str =
+    "https://api.bilibili.com/x/polymer/web-space/seasons_archives_list";
+/// ?mid={}&series_id={}&pn={}&ps={}&sort=asc
+pub const SERIES_ARCHIVES_API: &str = "https://api.bilibili.com/x/series/archives";
+/// ?series_id={}
+pub const SERIES_INFO_API: &str = "https://api.bilibili.com/x/series/series";
+/// favorite folders returns at most 20 videos per page
+const FAV_PAGE_SIZE: u64 = 20;
+const PAGE_SIZE: u64 = 30;
+
+/// A extractor for favorite folders (`/medialist/detail/ml{fid}` or `favlist?fid={fid}`).
+/// Private folders work only when cookies are passed.
+#[derive(Debug)]
+pub struct Favorites {
+    client: Client,
+    fid: u64,
+}
+
+/// A extractor for watch later list.
+/// Works only when cookies are passed.
+#[derive(Debug)]
+pub struct WatchLater {
+    client: Client,
+}
+
+/// A extractor for ugc seasons (合集)
+#[derive(Debug)]
+pub struct UgcSeason {
+    client: Client,
+    mid: u64,
+    season_id: u64,
+}
+
+/// A extractor for series lists
+#[derive(Debug)]
+pub struct Series {
+    client: Client,
+    mid: u64,
+    series_id: u64,
+}
+
+#[async_trait::async_trait]
+trait Paged {
+    /// Returns title of the list, videos on page `pn` (starting from 1) and whether more pages exist.
+    /// Pages may contain no videos after filtering, so only the flag ends the pagination
+    async fn page(&self, pn: u64) -> Result<(String, Vec<Upload>, bool), Error>;
+}
+
+async fn all_pages<P: Paged + Sync>(list: &P) -> Result<(String, Vec<Upload>), Error> {
+    let (title, mut uploads, mut more) = list.page(1).await?;
+    let mut pn = 1;
+    while more {
+        pn += 1;
+        let (_, page, has_more) = list.page(pn).await?;
+        more = has_more;
+        uploads.extend(page);
+    }
+    Ok((title, uploads))
+}
+
+/// Parses `space.bilibili.com/{mid}/...?sid={sid}` and `space.bilibili.com/{mid}/lists/{sid}`
+fn space_list_ids(url: &Url) -> Option<(u64, u64)> {
+    let mut segments = url.path_segments()?.filter(|s| !s.is_empty());
+    let mid = segments.next()?.parse().ok()?;
+    let sid = url
+        .query_pairs()
+        .find_map(|(k, v)| if k == "sid" { v.parse().ok() } else { None })
+        .or_else(|| match (segments.next(), segments.next()) {
+            (Some("lists"), Some(sid)) => sid.parse().ok(),
+            _ => None,
+        })?;
+    Some((mid, sid))
+}
+
+fn archives(data: &Value) -> Result<Vec<Upload>, Error> {
+    match data["data"]["archives"] {
+        Value::Array(ref archives) => archives.iter().map(Upload::from_json).collect(),
+        Value::Null => Ok(Vec::new()),
+        _ => err::InvalidResponse { resp: data.clone() }.fail(),
+    }
+}
+
+impl Favorites {
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url = Url::parse(s)?;
+        url.query_pairs()
+            .find_map(|(k, v)| if k == "fid" { v.parse().ok() } else { None })
+            .or_else(|| {
+                url.path_segments()
+                    .and_then(|mut it| it.rfind(|p| !p.is_empty()))
+                    .and_then(|p| p.strip_prefix("ml"))
+                    .and_then(|fid| fid.parse().ok())
+            })
+            .map(Self::with_fid)
+            .ok_or(Error::InvalidUrl { url })
+    }
+    pub fn with_fid(fid: u64) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), fid)
+    }
+    pub fn with_client(client: Client, fid: u64) -> Self {
+        Self { client, fid }
+    }
+    pub async fn uploads(&self) -> Result<Vec<Upload>, Error> {
+        Ok(all_pages(self).await?.1)
+    }
+}
+
+#[async_trait::async_trait]
+impl Paged for Favorites {
+    async fn page(&self, pn: u64) -> Result<(String, Vec<Upload>, bool), Error> {
+        let url = Url::parse_with_params(
+            FAV_API,
+            [
+                ("media_id", self.fid.to_string()),
+                ("pn", pn.to_string()),
+                ("ps", FAV_PAGE_SIZE.to_string()),
+                ("platform", "web".to_owned()),
+            ],
+        )?;
+        let data = check_code(self.client.send_json_request(url).await?)?;
+        let medias = match data["data"]["medias"] {
+            Value::Array(ref medias) => medias.as_slice(),
+            Value::Null => &[],
+            _ => return err::InvalidResponse { resp: data }.fail(),
+        };
+        let uploads = medias
+            .iter()
+            // type 2 is video, others are audio or whole ugc seasons
+            .filter(|media| media["type"].as_u64() == Some(2))
+            .map(
+                |media| match (media["id"].as_u64(), media["bvid"].as_str()) {
+                    (Some(aid), Some(bvid)) => Ok(Upload {
+                        aid,
+                        bvid: bvid.to_owned(),
+                        title: media["title"].as_str().unwrap_or_default().to_owned(),
+                    }),
+                    _ => err::InvalidResponse {
+                        resp: media.clone(),
+                    }
+                    .fail(),
+                },
+            )
+            .collect::<Result<_, Error>>()?;
+        let title = data["data"]["info"]["title"]
+            .as_str()
+            .unwrap_or_default()
+            .to_owned();
+        // checked before filtering, as a page may hold no videos at all
+        let more = data["data"]["has_more"].as_bool().unwrap_or(false) && !medias.is_empty();
+        Ok((title, uploads, more))
+    }
+}
+
+impl WatchLater {
+    pub fn new() -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()))
+    }
+    pub fn with_client(client: Client) -> Self {
+        Self { client }
+    }
+    pub async fn uploads(&self) -> Result<Vec<Upload>, Error> {
+        Ok(all_pages(self).await?.1)
+    }
+}
+
+impl Default for WatchLater {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl Paged for WatchLater {
+    async fn page(&self, _: u64) -> Result<(String, Vec<Upload>, bool), Error> {
+        let data = check_code(
+            self.client
+                .send_json_request(WATCH_LATER_API.parse()?)
+                .await?,
+        )?;
+        let uploads = match data["data"]["list"] {
+            Value::Array(ref list) => list
+                .iter()
+                .map(Upload::from_json)
+                .collect::<Result<_, Error>>()?,
+            Value::Null => Vec::new(),
+            _ => return err::InvalidResponse { resp: data }.fail(),
+        };
+        // the whole list is returned at once
+        Ok(("稍后再看".to_owned(), uploads, false))
+    }
+}
+
+impl UgcSeason {
+    /// Accepts `space.bilibili.com/{mid}/channel/collectiondetail?sid={season_id}`
+    /// and `space.bilibili.com/{mid}/lists/{season_id}?type=season`
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url = Url::parse(s)?;
+        space_list_ids(&url)
+            .map(|(mid, season_id)| Self::with_id(mid, season_id))
+            .ok_or(Error::InvalidUrl { url })
+    }
+    pub fn with_id(mid: u64, season_id: u64) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), mid, season_id)
+    }
+    pub fn with_client(client: Client, mid: u64, season_id: u64) -> Self {
+        Self {
+            client,
+            mid,
+            season_id,
+        }
+    }
+    /// Returns the ugc season a video belongs to, using `data` from `Video::video_info_json`
+    pub fn from_video_info(client: Client, info: &Value) -> Option<Self> {
+        let season_id = info["data"]["ugc_season"]["id"].as_u64()?;
+        let mid = info["data"]["ugc_season"]["mid"]
+            .as_u64()
+            .or_else(|| info["data"]["owner"]["mid"].as_u64())?;
+        Some(Self::with_client(client, mid, season_id))
+    }
+    pub async fn uploads(&self) -> Result<Vec<Upload>, Error> {
+        Ok(all_pages(self).await?.1)
+    }
+}
+
+#[async_trait::async_trait]
+impl Paged for UgcSeason {
+    async fn page(&self, pn: u64) -> Result<(String, Vec<Upload>, bool), Error> {
+        let url = Url::parse_with_params(
+            SEASON_ARCHIVES_API,
+            [
+                ("mid", self.mid.to_string()),
+                ("season_id", self.season_id.to_string()),
+                ("page_num", pn.to_string()),
+                ("page_size", PAGE_SIZE.to_string()),
+                ("sort_reverse", "false".to_owned()),
+            ],
+        )?;
+        let data = check_code(self.client.send_json_request(url).await?)?;
+        let uploads = archives(&data)?;
+        let title = data["data"]["meta"]["name"]
+            .as_str()
+            .unwrap_or_default()
+            .to_owned();
+        let total = data["data"]["page"]["total"].as_u64().unwrap_or(0);
+        Ok((title, uploads, pn * PAGE_SIZE < total))
+    }
+}
+
+impl Series {
+    /// Accepts `space.bilibili.com/{mid}/channel/seriesdetail?sid={series_id}`
+    /// and `space.bilibili.com/{mid}/lists/{series_id}?type=series`
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url = Url::parse(s)?;
+        space_list_ids(&url)
+            .map(|(mid, series_id)| Self::with_id(mid, series_id))
+            .ok_or(Error::InvalidUrl { url })
+    }
+    pub fn with_id(mid: u64, series_id: u64) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), mid, series_id)
+    }
+    pub fn with_client(client: Client, mid: u64, series_id: u64) -> Self {
+        Self {
+            client,
+            mid,
+            series_id,
+        }
+    }
+    pub async fn title(&self) -> Result<String, Error> {
+        let url = format!("{}?series_id={}", SERIES_INFO_API, self.series_id).parse()?;
+        let data = check_code(self.client.send_json_request(url).await?)?;
+        match data["data"]["meta"]["name"] {
+            Value::String(ref name) => Ok(name.to_owned()),
+            _ => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+    pub async fn uploads(&self) -> Result<Vec<Upload>, Error> {
+        Ok(all_pages(self).await?.1)
+    }
+}
+
+#[async_trait::async_trait]
+impl Paged for Series {
+    async fn page(&self, pn: u64) -> Result<(String, Vec<Upload>, bool), Error> {
+        let url = Url::parse_with_params(
+            SERIES_ARCHIVES_API,
+            [
+                ("mid", self.mid.to_string()),
+                ("series_id", self.series_id.to_string()),
+                ("pn", pn.to_string()),
+                ("ps", PAGE_SIZE.to_string()),
+                ("sort", "asc".to_owned()),
+            ],
+        )?;
+        let data = check_code(self.client.send_json_request(url).await?)?;
+        let uploads = archives(&data)?;
+        let title = match pn {
+            1 => self.title().await.unwrap_or_default(),
+            _ => String::new(),
+        };
+        let total = data["data"]["page"]["total"].as_u64().unwrap_or(0);
+        Ok((title, uploads, pn * PAGE_SIZE < total))
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for Favorites {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let (title, uploads) = all_pages(self).await?;
+        let origins = extract_uploads(&self.client, &uploads).await?;
+        Ok(Playlist::new(origins, title))
+    }
+}
+
+impl AsClient for Favorites {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for WatchLater {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let (title, uploads) = all_pages(self).await?;
+        let origins = extract_uploads(&self.client, &uploads).await?;
+        Ok(Playlist::new(origins, title))
+    }
+}
+
+impl AsClient for WatchLater {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for UgcSeason {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let (title, uploads) = all_pages(self).await?;
+        let origins = extract_uploads(&self.client, &uploads).await?;
+        Ok(Playlist::new(origins, title))
+    }
+}
+
+impl AsClient for UgcSeason {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for Series {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let (title, uploads) = all_pages(self).await?;
+        let origins = extract_uploads(&self.client, &uploads).await?;
+        Ok(Playlist::new(origins, title))
+    }
+}
+
+impl AsClient for Series {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
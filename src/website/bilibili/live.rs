@@ -0,0 +1,331 @@
+use super::check_code;
+use crate::{error as err, utils::Client, AsClient, Error, Extract, Meta, Origin, Playlist, Track};
+use serde_json::Value;
+use url::Url;
+
+/// ?room_id={}&protocol={}&format={}&codec={}&qn={}&platform=web
+pub const LIVE_API: &str = "https://api.live.bilibili.com/xlive/web-room/v2/index/getRoomPlayInfo";
+/// ?id={}, resolves short room ids
+pub const LIVE_INFO_API: &str = "https://api.live.bilibili.com/room/v1/Room/room_init";
+/// ?mid={}
+pub const SPACE_INFO_API: &str = "https://api.bilibili.com/x/space/acc/info";
+/// quality number of the original stream
+pub const QN_ORIGINAL: u64 = 10000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Protocol {
+    /// flv over http
+    #[default]
+    HttpFlv,
+    HttpHls,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Flv,
+    Ts,
+    Fmp4,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Codec {
+    #[default]
+    Avc,
+    Hevc,
+}
+
+/// Stream to request from `getRoomPlayInfo`.
+/// Note that flv is only served with `Protocol::HttpFlv`, and ts and fmp4 with `Protocol::HttpHls`.
+/// If the codec or quality is unavailable, another one is served instead, preferring avc.
+/// The stream served is put into the meta as `protocol`, `format`, `codec` and `qn`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamOptions {
+    pub protocol: Protocol,
+    pub format: Format,
+    pub codec: Codec,
+    /// 10000 for original, 400 for blu-ray, 250 for ultra, 150 for high and 80 for fluent
+    pub qn: u64,
+}
+
+pub struct BaseLiveExtractor {
+    cid: u64,
+    client: Client,
+    stream: StreamOptions,
+}
+
+pub struct Live {
+    id: u64,
+    client: Client,
+    stream: StreamOptions,
+}
+
+impl Protocol {
+    fn as_param(&self) -> u8 {
+        *self as u8
+    }
+    fn name(&self) -> &'static str {
+        match self {
+            Self::HttpFlv => "http_stream",
+            Self::HttpHls => "http_hls",
+        }
+    }
+}
+
+impl Format {
+    fn as_param(&self) -> u8 {
+        *self as u8
+    }
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Flv => "flv",
+            Self::Ts => "ts",
+            Self::Fmp4 => "fmp4",
+        }
+    }
+}
+
+impl Codec {
+    fn as_param(&self) -> u8 {
+        *self as u8
+    }
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Avc => "avc",
+            Self::Hevc => "hevc",
+        }
+    }
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            protocol: Protocol::default(),
+            format: Format::default(),
+            codec: Codec::default(),
+            qn: QN_ORIGINAL,
+        }
+    }
+}
+
+impl StreamOptions {
+    pub fn hls(format: Format) -> Self {
+        Self {
+            protocol: Protocol::HttpHls,
+            format,
+            ..Self::default()
+        }
+    }
+    pub fn codec(mut self, codec: Codec) -> Self {
+        self.codec = codec;
+        self
+    }
+    pub fn qn(mut self, qn: u64) -> Self {
+        self.qn = qn;
+        self
+    }
+}
+
+impl BaseLiveExtractor {
+    /// `cid` is the room id, short ids are accepted as well
+    pub fn new(cid: u64, client: Client) -> Self {
+        Self {
+            cid,
+            client,
+            stream: StreamOptions::default(),
+        }
+    }
+    pub fn with_stream(mut self, stream: StreamOptions) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    pub fn as_live_url(&self) -> String {
+        let cid = self.cid;
+        let StreamOptions {
+            protocol,
+            format,
+            codec,
+            qn,
+        } = self.stream;
+        format!(
+            "{LIVE_API}?room_id={cid}&protocol={}&format={}&codec={}&qn={qn}&platform=web&ptype=8",
+            protocol.as_param(),
+            format.as_param(),
+            codec.as_param(),
+        )
+    }
+
+    async fn _extract(&mut self) -> crate::FinaResult {
+        let url = self.as_live_url();
+        let data = check_code(self.client.send_json_request(url.parse()?).await?)?;
+        // 0 for offline, 2 for replaying videos in turn
+        if data["data"]["live_status"].as_u64() != Some(1) {
+            let room = data["data"]["room_id"].as_u64().unwrap_or(self.cid);
+            return err::LiveOffline {
+                room: room.to_string(),
+            }
+            .fail();
+        }
+        let origin = play_origin(&data, &self.stream)?;
+        Ok(Playlist::new(vec![origin], String::new()))
+    }
+}
+
+/// Picks the stream closest to `stream` from the response of `LIVE_API`
+pub fn play_origin(data: &Value, stream: &StreamOptions) -> Result<Origin, Error> {
+    let codec = match select_codec(&data["data"]["playurl_info"]["playurl"], stream) {
+        Some(codec) => codec,
+        None => return err::InvalidResponse { resp: data.clone() }.fail(),
+    };
+    let base_url = codec["base_url"].as_str().unwrap_or_default();
+    let mirrors = match codec["url_info"] {
+        Value::Array(ref hosts) => hosts
+            .iter()
+            .map(|host| {
+                Url::parse(&format!(
+                    "{}{}{}",
+                    host["host"].as_str().unwrap_or_default(),
+                    base_url,
+                    host["extra"].as_str().unwrap_or_default()
+                ))
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => Vec::new(),
+    };
+    let live_url = match mirrors.first() {
+        Some(url) => url.clone(),
+        None => return err::InvalidResponse { resp: data.clone() }.fail(),
+    };
+    let mut meta = Meta::new();
+    meta.insert("room_id".to_owned(), data["data"]["room_id"].clone());
+    meta.insert("protocol".to_owned(), stream.protocol.name().into());
+    meta.insert("format".to_owned(), stream.format.name().into());
+    meta.insert("qn".to_owned(), codec["current_qn"].clone());
+    meta.insert("accept_qn".to_owned(), codec["accept_qn"].clone());
+    meta.insert("codec".to_owned(), codec["codec_name"].clone());
+    meta.insert(
+        "mirrors".to_owned(),
+        mirrors.iter().map(|url| url.as_str()).collect(),
+    );
+    Ok(Origin::new(vec![Track::Video(live_url)], String::new()).with_meta(meta))
+}
+
+/// Finds the codec matching `stream`, preferring avc if the requested codec is unavailable
+fn select_codec<'a>(playurl: &'a Value, stream: &StreamOptions) -> Option<&'a Value> {
+    let codecs = playurl["stream"]
+        .as_array()?
+        .iter()
+        .filter(|s| s["protocol_name"] == stream.protocol.name())
+        .flat_map(|s| s["format"].as_array().into_iter().flatten())
+        .filter(|f| f["format_name"] == stream.format.name())
+        .flat_map(|f| f["codec"].as_array().into_iter().flatten())
+        .collect::<Vec<_>>();
+    codecs
+        .iter()
+        .find(|c| c["codec_name"] == stream.codec.name())
+        .or_else(|| codecs.iter().find(|c| c["codec_name"] == Codec::Avc.name()))
+        .or_else(|| codecs.first())
+        .copied()
+}
+
+#[async_trait::async_trait]
+impl Extract for BaseLiveExtractor {
+    async fn extract(&mut self) -> crate::FinaResult {
+        self._extract().await
+    }
+}
+
+impl AsClient for BaseLiveExtractor {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
+
+impl Live {
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url = Url::parse(s)?;
+        let id = url
+            .path_segments()
+            .and_then(|mut it| it.rfind(|p| !p.is_empty()))
+            .ok_or_else(|| err::InvalidUrl { url: url.clone() }.build())?
+            .parse()
+            .map_err(|_| err::InvalidUrl { url }.build())?;
+        Ok(Self::with_id(id))
+    }
+    pub fn with_id(id: u64) -> Self {
+        Self::with_client(Client::new(), id)
+    }
+    pub fn with_client(client: Client, id: u64) -> Self {
+        Self {
+            id,
+            client,
+            stream: StreamOptions::default(),
+        }
+    }
+    pub fn with_stream(mut self, stream: StreamOptions) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    pub fn as_info_url(&self) -> String {
+        let id = self.id;
+        format!("{LIVE_INFO_API}?id={id}")
+    }
+
+    /// Resolves the room id, which may be a short one, into `(room_id, uid, live_status)`
+    pub async fn room_info(&self) -> Result<(u64, u64, u64), Error> {
+        let url = self.as_info_url();
+        let data = check_code(self.client.send_json_request(url.parse()?).await?)?;
+        match (
+            data["data"]["room_id"].as_u64(),
+            data["data"]["uid"].as_u64(),
+            data["data"]["live_status"].as_u64(),
+        ) {
+            (Some(room_id), Some(uid), Some(status)) => Ok((room_id, uid, status)),
+            _ => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+
+    async fn title(&self, uid: u64) -> Result<String, Error> {
+        let url = format!("{SPACE_INFO_API}?mid={uid}");
+        let data: Value = self.client.send_json_request(url.parse()?).await?;
+        match data["data"]["live_room"]["title"] {
+            Value::String(ref title) => Ok(title.to_owned()),
+            _ => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+
+    async fn _extract(&self) -> crate::FinaResult {
+        let (cid, uid, status) = self.room_info().await?;
+        if status != 1 {
+            return err::LiveOffline {
+                room: cid.to_string(),
+            }
+            .fail();
+        }
+        let mut base_extor =
+            BaseLiveExtractor::new(cid, self.client.clone()).with_stream(self.stream);
+        let (raw, _) = base_extor.extract().await?.into_parts();
+        Ok(Playlist::new(raw, self.title(uid).await?))
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for Live {
+    async fn extract(&mut self) -> crate::FinaResult {
+        self._extract().await
+    }
+}
+
+impl AsClient for Live {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
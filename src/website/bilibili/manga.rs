@@ -0,0 +1,221 @@
+//! Extractor for manga (漫画) chapters and whole comics
+use super::{check_code, is_unavailable, unavailable, CONCURRENCY};
+use crate::{
+    error as err, utils, utils::Client, AsClient, Error, Extract, Meta, Origin, Playlist, Track,
+};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use once_cell::sync::Lazy;
+use reqwest::header::{self, HeaderMap};
+use serde_json::{json, Value};
+use url::Url;
+
+/// post {"comic_id": comic_id}
+pub const COMIC_DETAIL_API: &str =
+    "https://manga.bilibili.com/twirp/comic.v1.Comic/ComicDetail?device=pc&platform=web";
+/// post {"ep_id": ep_id}
+pub const IMAGE_INDEX_API: &str =
+    "https://manga.bilibili.com/twirp/comic.v1.Comic/GetImageIndex?device=pc&platform=web";
+/// post {"urls": "[\"path\", ...]"}
+pub const IMAGE_TOKEN_API: &str =
+    "https://manga.bilibili.com/twirp/comic.v1.Comic/ImageToken?device=pc&platform=web";
+
+static HEADERS: Lazy<HeaderMap> = Lazy::new(|| {
+    crate::hdmap! {
+        header::USER_AGENT => utils::UA.clone(),
+        header::REFERER => "https://manga.bilibili.com/",
+    }
+});
+
+/// A extractor for manga (`manga.bilibili.com/mc{comic_id}/{ep_id}` for a chapter,
+/// `manga.bilibili.com/detail/mc{comic_id}` for the whole comic).
+/// Paid chapters are available only when cookies of an account that purchased them are passed.
+/// Locked chapters are kept in whole comics as origins without tracks and with `locked` set
+/// in their meta, while extracting a locked chapter alone fails with `Error::PurchaseRequired`.
+/// Other chapters that fail to extract are kept without tracks and marked `unavailable`
+#[derive(Debug)]
+pub struct Manga {
+    client: Client,
+    comic_id: u64,
+    ep_id: Option<u64>,
+    concurrency: usize,
+}
+
+impl Manga {
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url = Url::parse(s)?;
+        let mut segments = url
+            .path_segments()
+            .into_iter()
+            .flatten()
+            .skip_while(|p| !p.starts_with("mc"));
+        let comic_id = segments
+            .next()
+            .and_then(|p| p.strip_prefix("mc"))
+            .and_then(|id| id.parse().ok());
+        let ep_id = segments.next().and_then(|id| id.parse().ok());
+        match comic_id {
+            Some(comic_id) => Ok(Self::with_id(comic_id, ep_id)),
+            None => Err(Error::InvalidUrl { url }),
+        }
+    }
+    /// Extracts the whole comic if `ep_id` is `None`
+    pub fn with_id(comic_id: u64, ep_id: Option<u64>) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), comic_id, ep_id)
+    }
+    pub fn with_client(client: Client, comic_id: u64, ep_id: Option<u64>) -> Self {
+        Self {
+            client,
+            comic_id,
+            ep_id,
+            concurrency: CONCURRENCY,
+        }
+    }
+    /// Number of chapters extracted at the same time, 4 by default
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    pub async fn detail_json(&self) -> Result<Value, Error> {
+        let data = self
+            .client
+            .post_json_body(
+                COMIC_DETAIL_API.parse()?,
+                &json!({ "comic_id": self.comic_id }),
+            )
+            .await?;
+        let data = check_code(data)?;
+        match data["data"]["ep_list"] {
+            Value::Array(_) => Ok(data),
+            _ => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+    /// Returns urls of pages in a chapter, with tokens attached
+    pub async fn pages(&self, ep_id: u64) -> Result<Vec<Url>, Error> {
+        let index = self
+            .client
+            .post_json_body(IMAGE_INDEX_API.parse()?, &json!({ "ep_id": ep_id }))
+            .await?;
+        let index = check_code(index)?;
+        let paths = match index["data"]["images"] {
+            Value::Array(ref images) => images
+                .iter()
+                .filter_map(|image| image["path"].as_str())
+                .collect::<Vec<_>>(),
+            _ => return err::InvalidResponse { resp: index }.fail(),
+        };
+        // `urls` is a json array encoded as string
+        let urls = serde_json::to_string(&paths).unwrap_or_default();
+        let tokens = self
+            .client
+            .post_json_body(IMAGE_TOKEN_API.parse()?, &json!({ "urls": urls }))
+            .await?;
+        let tokens = check_code(tokens)?;
+        match tokens["data"] {
+            Value::Array(ref tokens) if tokens.len() == paths.len() => tokens
+                .iter()
+                .map(
+                    |token| match (token["url"].as_str(), token["token"].as_str()) {
+                        (Some(url), Some(token)) => {
+                            Ok(Url::parse_with_params(url, [("token", token)])?)
+                        }
+                        _ => err::InvalidResponse {
+                            resp: token.clone(),
+                        }
+                        .fail(),
+                    },
+                )
+                .collect(),
+            _ => err::InvalidResponse { resp: tokens }.fail(),
+        }
+    }
+    /// Extracts a chapter, `ep` being an item in `ep_list` of `detail_json`
+    async fn chapter(&self, ep: &Value) -> Result<Origin, Error> {
+        let ep_id = match ep["id"].as_u64() {
+            Some(ep_id) => ep_id,
+            None => return err::InvalidResponse { resp: ep.clone() }.fail(),
+        };
+        let locked = ep["is_locked"].as_bool().unwrap_or(false);
+        let tracks = if locked {
+            Vec::new()
+        } else {
+            self.pages(ep_id)
+                .await?
+                .into_iter()
+                .map(Track::Image)
+                .collect()
+        };
+        let mut meta = Meta::new();
+        meta.insert("ep_id".to_owned(), ep_id.into());
+        meta.insert("ord".to_owned(), ep["ord"].clone());
+        meta.insert("locked".to_owned(), locked.into());
+        Ok(Origin::new(tracks, chapter_title(ep)).with_meta(meta))
+    }
+}
+
+/// Joins short title and title of a chapter, e.g. "1 序章"
+fn chapter_title(ep: &Value) -> String {
+    let short_title = ep["short_title"].as_str().unwrap_or_default();
+    let title = ep["title"].as_str().unwrap_or_default();
+    format!("{} {}", short_title, title).trim().to_owned()
+}
+
+#[async_trait::async_trait]
+impl Extract for Manga {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let data = self.detail_json().await?;
+        let title = data["data"]["title"]
+            .as_str()
+            .unwrap_or_default()
+            .to_owned();
+        let eps = data["data"]["ep_list"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        let origins = match self.ep_id {
+            Some(ep_id) => {
+                let ep = match eps.iter().find(|ep| ep["id"].as_u64() == Some(ep_id)) {
+                    Some(ep) => ep,
+                    None => return err::InvalidResponse { resp: data }.fail(),
+                };
+                if ep["is_locked"].as_bool() == Some(true) {
+                    return err::PurchaseRequired { resp: ep.clone() }.fail();
+                }
+                vec![self.chapter(ep).await?]
+            }
+            None => {
+                // `ep_list` is ordered from the latest chapter
+                let mut eps = eps;
+                eps.sort_by(|a, b| {
+                    let ord = |ep: &Value| ep["ord"].as_f64().unwrap_or_default();
+                    ord(a).total_cmp(&ord(b))
+                });
+                let this = &*self;
+                let extractions = eps.into_iter().map(|ep| async move {
+                    match this.chapter(&ep).await {
+                        Err(e) if is_unavailable(&e) => {
+                            let mut origin = unavailable(chapter_title(&ep));
+                            origin.meta.insert("ep_id".to_owned(), ep["id"].clone());
+                            origin.meta.insert("ord".to_owned(), ep["ord"].clone());
+                            Ok(origin)
+                        }
+                        res => res,
+                    }
+                });
+                stream::iter(extractions)
+                    .buffered(self.concurrency)
+                    .try_collect()
+                    .await?
+            }
+        };
+        Ok(Playlist::new(origins, title))
+    }
+}
+
+impl AsClient for Manga {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
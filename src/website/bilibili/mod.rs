@@ -0,0 +1,653 @@
+use crate::{
+    error as err, utils, utils::Client, AsClient, Error, Extract, Meta, Origin, Playlist, Track,
+};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use once_cell::sync::Lazy;
+use reqwest::header::{self, HeaderMap};
+use serde_json::Value;
+use url::Url;
+
+pub mod audio;
+pub mod bv;
+pub mod cheese;
+pub mod danmaku;
+pub mod dash;
+pub mod dynamic;
+pub mod interactive;
+pub mod list;
+pub mod live;
+pub mod manga;
+pub mod monitor;
+pub mod space;
+pub mod wbi;
+
+pub use audio::{Audio, AudioMenu, AudioQuality};
+pub use cheese::Course;
+pub use danmaku::Danmaku;
+pub use dynamic::{Article, Dynamic};
+pub use interactive::Interactive;
+pub use list::{Favorites, Series, UgcSeason, WatchLater};
+pub use live::{
+    BaseLiveExtractor, Codec, Format, Live, Protocol, StreamOptions, LIVE_API, LIVE_INFO_API,
+    SPACE_INFO_API,
+};
+pub use manga::Manga;
+pub use monitor::Monitor;
+pub use space::{Order, Space, Upload};
+
+static HEADERS: Lazy<HeaderMap> = Lazy::new(|| {
+    crate::hdmap! {
+        header::USER_AGENT => utils::UA.clone(),
+        header::REFERER => "https://www.bilibili.com",
+    }
+});
+
+/// Number of items extracted at the same time by default
+const CONCURRENCY: usize = 4;
+
+/// add ?bvid={} or ?aid={}
+const CID_API: &str = "https://api.bilibili.com/x/player/pagelist";
+/// add ?season_id={} or ?ep_id={}
+const BANGUMI_CID_API: &str = "https://api.bilibili.com/pgc/view/web/season";
+/// ?media_id={}
+const BANGUMI_MEDIA_API: &str = "https://api.bilibili.com/pgc/review/user";
+/// ?cid={}&ep_id={}
+const BANGUMI_VIDEO_API: &str = "https://api.bilibili.com/pgc/player/web/playurl";
+/// ?cid={}&qn={}&avid={} or ?cid={}&qn={}&bvid={}
+const VIDEO_API: &str = "https://api.bilibili.com/x/player/playurl";
+/// ?bvid={} or ?aid={}
+pub const VIDEO_INFO_API: &str = "https://api.bilibili.com/x/web-interface/view";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Id {
+    Av(u64),
+    Bv(String),
+    Ep(u64),
+    Ss(u64),
+    Md(u64),
+}
+
+pub struct BaseExtractor {
+    aid: u64,
+    cid: u64,
+    client: Client,
+}
+
+pub struct Video {
+    client: Client,
+    id: Id,
+    page: Option<usize>,
+}
+
+pub struct Bangumi {
+    client: Client,
+    id: Id,
+    season: bool,
+    sections: bool,
+    concurrency: usize,
+}
+
+impl Id {
+    pub fn from_url(url: &Url) -> Result<Self, Error> {
+        url.path_segments()
+            .and_then(|mut it| it.rfind(|p| !p.is_empty()))
+            .and_then(Self::new)
+            .ok_or_else(|| Error::InvalidUrl {
+                url: url.to_owned(),
+            })
+    }
+    pub fn new(id: &str) -> Option<Self> {
+        if id.starts_with("av") {
+            id.trim_start_matches("av").parse().ok().map(Self::Av)
+        } else if id.starts_with("BV") {
+            Some(id)
+                .filter(|id| bv::is_valid_bv(id))
+                .map(|id| Self::Bv(id.to_owned()))
+        } else if id.starts_with("ep") {
+            id.trim_start_matches("ep").parse().ok().map(Self::Ep)
+        } else if id.starts_with("ss") {
+            id.trim_start_matches("ss").parse().ok().map(Self::Ss)
+        } else if id.starts_with("md") {
+            id.trim_start_matches("md").parse().ok().map(Self::Md)
+        } else {
+            None
+        }
+    }
+    /// Returns av number of a video, converting BV id offline
+    pub fn aid(&self) -> Option<u64> {
+        match self {
+            Self::Av(av) => Some(*av),
+            Self::Bv(bv) => bv::bv_to_av(bv),
+            _ => None,
+        }
+    }
+    /// Returns BV id of a video, converting av number offline
+    pub fn bvid(&self) -> Option<String> {
+        match self {
+            Self::Av(av) => bv::av_to_bv(*av),
+            Self::Bv(bv) => Some(bv.to_owned()),
+            _ => None,
+        }
+    }
+    /// Turns video ids into `Id::Av`, so that the same video always has the same id
+    pub fn normalize(self) -> Self {
+        match self.aid() {
+            Some(aid) => Self::Av(aid),
+            None => self,
+        }
+    }
+    /// A media id has to be resolved into a season id first, see `Bangumi::season_id`
+    fn as_cid_api(&self) -> Result<Url, Error> {
+        Ok(match self {
+            Self::Av(av) => format!("{}?aid={}", CID_API, av),
+            Self::Bv(bv) => format!("{}?bvid={}", CID_API, bv),
+            Self::Ep(ep) => format!("{}?ep_id={}", BANGUMI_CID_API, ep),
+            Self::Ss(ss) => format!("{}?season_id={}", BANGUMI_CID_API, ss),
+            Self::Md(md) => {
+                return Err(Error::InvalidUrl {
+                    url: format!("{}?media_id={}", BANGUMI_MEDIA_API, md).parse()?,
+                })
+            }
+        }
+        .parse()?)
+    }
+    fn as_video_api(&self, cid: u64) -> Result<Url, Error> {
+        Ok(match self {
+            Id::Av(ref avid) => format!(
+                "{}?cid={}&fnval={}&fourk=1&avid={}",
+                VIDEO_API,
+                cid,
+                dash::FNVAL_ALL,
+                avid
+            ),
+            Id::Bv(ref bvid) => format!(
+                "{}?cid={}&fnval={}&fourk=1&bvid={}",
+                VIDEO_API,
+                cid,
+                dash::FNVAL_ALL,
+                bvid
+            ),
+            Id::Ep(ref epid) => format!(
+                "{}?cid={}&fnval={}&fourk=1&ep_id={}",
+                BANGUMI_VIDEO_API,
+                cid,
+                dash::FNVAL_ALL,
+                epid
+            ),
+            // a season has no single video
+            Id::Ss(ss) => {
+                return Err(Error::InvalidUrl {
+                    url: format!("{}?season_id={}", BANGUMI_CID_API, ss).parse()?,
+                })
+            }
+            Id::Md(md) => {
+                return Err(Error::InvalidUrl {
+                    url: format!("{}?media_id={}", BANGUMI_MEDIA_API, md).parse()?,
+                })
+            }
+        }
+        .parse()?)
+    }
+}
+
+impl BaseExtractor {
+    fn as_video_api(&self) -> String {
+        format!(
+            "{}?cid={}&qn=127&fnval={}&fourk=1&avid={}",
+            VIDEO_API,
+            self.cid,
+            dash::FNVAL_ALL,
+            self.aid
+        )
+    }
+    pub fn new(aid: u64, cid: u64, client: Client) -> Self {
+        Self { aid, cid, client }
+    }
+    pub async fn title(&mut self) -> Result<String, Error> {
+        let url = format!("{}?aid={}", VIDEO_INFO_API, self.aid).parse()?;
+        let info: Value = self.client.send_json_request(url).await?;
+        match info["data"]["title"] {
+            Value::String(ref s) => Ok(s.to_owned()),
+            _ => err::InvalidResponse { resp: info }.fail(),
+        }
+    }
+    /// Extracts the best video and audio available, see `dash::streams` for the others.
+    /// The meta of the `Origin` has the `dynamic_range` and `audio_format` of the tracks,
+    /// and all dash streams under `streams`
+    pub async fn origin(&self) -> Result<Origin, Error> {
+        let url = self.as_video_api();
+        let data = check_code(self.client.send_json_request(Url::parse(&url)?).await?)?;
+        match parse_dash(&data["data"]["dash"])
+            .transpose()
+            .or_else(|| parse_durl(&data["data"]["durl"]).transpose())
+        {
+            Some(Ok(origin)) => Ok(origin),
+            Some(Err(_)) | None => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+    pub async fn tracks(&self) -> Result<Vec<Track>, Error> {
+        Ok(self.origin().await?.tracks)
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for BaseExtractor {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let origin = self.origin().await?;
+        let title = self.title().await.unwrap_or_default();
+        Ok(Playlist::new(vec![origin], title))
+    }
+}
+
+impl Video {
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url: Url = Url::parse(s)?;
+        let id = Id::from_url(&url)?;
+        let page = url
+            .query_pairs()
+            .find_map(|(key, v)| if key == "p" { v.parse().ok() } else { None });
+        match id {
+            Id::Av(_) | Id::Bv(_) => Ok(Self::with_id(id, page)),
+            _ => Err(Error::InvalidUrl { url }),
+        }
+    }
+    pub fn with_id(id: Id, page: Option<usize>) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), id, page)
+    }
+    pub fn with_client(client: Client, id: Id, page: Option<usize>) -> Self {
+        Self { client, id, page }
+    }
+    pub async fn playlist_json(&self) -> Result<Vec<Value>, Error> {
+        let url = self.id.as_cid_api()?;
+        let data: Value = self.client.send_json_request(url).await?;
+        match data["data"] {
+            Value::Array(ref cids) => Ok(cids.clone()),
+            _ => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+    /// Returns dash url
+    // todo: move this method into `BaseExtractor`
+    pub async fn video_url_json(&self, cid: u64) -> Result<Value, Error> {
+        let url = self.id.as_video_api(cid)?;
+        self.client.send_json_request(url).await
+    }
+    // todo: move this method into `BaseExtractor`
+    pub async fn video_dash_urls(&self, cid: u64) -> Result<Value, Error> {
+        let mut data = self.video_url_json(cid).await?;
+        match &mut data["data"]["dash"] {
+            Value::Null => err::InvalidResponse { resp: data }.fail(),
+            res => Ok(res.take()),
+        }
+    }
+    pub async fn video_info_json(&self) -> Result<Value, Error> {
+        let url = match &self.id {
+            Id::Av(av) => format!("{}?aid={}", VIDEO_INFO_API, av),
+            Id::Bv(bv) => format!("{}?bvid={}", VIDEO_INFO_API, bv),
+            _ => unreachable!(),
+        }
+        .parse()?;
+        self.client.send_json_request(url).await
+    }
+    pub async fn title(&self) -> Result<String, Error> {
+        let info = self.video_info_json().await?;
+        match info["data"]["title"] {
+            Value::String(ref s) => Ok(s.to_owned()),
+            _ => err::InvalidResponse { resp: info }.fail(),
+        }
+    }
+    /// Returns the ugc season (合集) the video belongs to, if any
+    pub async fn ugc_season(&self) -> Result<Option<UgcSeason>, Error> {
+        let info = check_code(self.video_info_json().await?)?;
+        Ok(UgcSeason::from_video_info(self.client.clone(), &info))
+    }
+    /// Extracts every page of the video, one `Origin` for each
+    pub async fn pages(&self) -> Result<Vec<Origin>, Error> {
+        let info = check_code(self.video_info_json().await?)?;
+        let (aid, pages) = match (info["data"]["aid"].as_u64(), &info["data"]["pages"]) {
+            (Some(aid), Value::Array(pages)) => (aid, pages),
+            _ => return err::InvalidResponse { resp: info }.fail(),
+        };
+        let title = info["data"]["title"].as_str().unwrap_or_default();
+        let mut origins = Vec::with_capacity(pages.len());
+        for page in pages {
+            let cid = extract_cid(page)?;
+            let mut origin = BaseExtractor::new(aid, cid, self.client.clone())
+                .origin()
+                .await?;
+            origin.title = match page["part"].as_str() {
+                Some(part) if pages.len() > 1 => format!("{} {}", title, part),
+                _ => title.to_owned(),
+            };
+            origin.meta.insert("aid".to_owned(), aid.into());
+            origin.meta.insert("cid".to_owned(), cid.into());
+            origin.meta.insert("page".to_owned(), page["page"].clone());
+            origins.push(origin);
+        }
+        Ok(origins)
+    }
+    async fn current_page(&self) -> Result<Value, Error> {
+        let playlist = self.playlist_json().await?;
+        match playlist.get(self.page.unwrap_or(1) - 1) {
+            Some(res) => Ok(res.clone()),
+            _ => err::InvalidResponse { resp: playlist }.fail(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for Video {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let page = self.current_page().await?;
+        // both av and BV ids are converted offline, other ids have no video to extract
+        let aid = match self.id.aid() {
+            Some(aid) => aid,
+            None => return err::InvalidResponse { resp: page }.fail(),
+        };
+        let cid = extract_cid(&page)?;
+        let mut base_extor = BaseExtractor::new(aid, cid, self.client.clone());
+        base_extor.extract().await
+    }
+}
+
+impl AsClient for Video {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
+
+impl Bangumi {
+    pub fn with_id(id: Id) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), id)
+    }
+    pub fn with_client(client: Client, id: Id) -> Self {
+        Self {
+            client,
+            id,
+            season: false,
+            sections: false,
+            concurrency: CONCURRENCY,
+        }
+    }
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url: Url = Url::parse(s)?;
+        let id = Id::from_url(&url)?;
+        match id {
+            Id::Ep(_) | Id::Ss(_) | Id::Md(_) => Ok(Self::with_id(id)),
+            _ => Err(Error::InvalidUrl { url }),
+        }
+    }
+    /// Extracts every episode of the season instead of a single one.
+    /// Episodes in sections (PVs, specials, etc.) are included if `sections` is true
+    /// Episodes which cannot be played, such as those for members only, are kept without tracks
+    /// and marked `unavailable` in the meta
+    pub fn extracts_season(mut self, sections: bool) -> Self {
+        self.season = true;
+        self.sections = sections;
+        self
+    }
+    /// Number of episodes extracted at the same time, 4 by default
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    /// Resolves a media id into the id of the season it belongs to
+    pub async fn season_id(&self, media_id: u64) -> Result<u64, Error> {
+        let url = format!("{}?media_id={}", BANGUMI_MEDIA_API, media_id).parse()?;
+        let data: Value = self.client.send_json_request(url).await?;
+        match data["result"]["media"]["season_id"].as_u64() {
+            Some(ssid) => Ok(ssid),
+            None => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+    pub async fn season_json(&self) -> Result<Value, Error> {
+        let url = match self.id {
+            Id::Md(mdid) => Id::Ss(self.season_id(mdid).await?).as_cid_api()?,
+            ref id => id.as_cid_api()?,
+        };
+        let data: Value = self.client.send_json_request(url).await?;
+        match data["result"] {
+            Value::Object(_) => Ok(data),
+            _ => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+    #[deprecated = "this method does not return a complete playlist"]
+    pub async fn playlist_json(&self) -> Result<Vec<Value>, Error> {
+        let url = match self.id {
+            Id::Md(mdid) => Id::Ss(self.season_id(mdid).await?).as_cid_api()?,
+            ref id => id.as_cid_api()?,
+        };
+        let data: Value = self.client.send_json_request(url).await?;
+        match data["result"]["episodes"] {
+            Value::Array(ref eps) => Ok(eps.clone()),
+            _ => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+    async fn current_page(&self) -> Result<Value, Error> {
+        let data = self.season_json().await?;
+        let playlist = episodes(&data["result"], true);
+        let page = match self.id {
+            Id::Ss(_) | Id::Md(_) => playlist.first(),
+            Id::Ep(epid) => playlist.iter().find(|ep| ep["id"].as_u64() == Some(epid)),
+            _ => unreachable!(),
+        };
+        match page {
+            Some(res) => Ok(res.to_owned()),
+            None => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+    async fn extract_season(&self) -> crate::FinaResult {
+        let data = self.season_json().await?;
+        let eps = episodes(&data["result"], self.sections);
+        let extractions = eps.into_iter().map(|ep| {
+            let client = self.client.clone();
+            async move {
+                let origin = match (ep["aid"].as_u64(), ep["cid"].as_u64()) {
+                    (Some(aid), Some(cid)) => BaseExtractor::new(aid, cid, client).origin().await,
+                    _ => err::InvalidResponse { resp: ep.clone() }.fail(),
+                };
+                let mut origin = match origin {
+                    Ok(origin) => origin,
+                    // e.g. episodes for members only
+                    Err(e) if is_unavailable(&e) => unavailable(String::new()),
+                    Err(e) => return Err(e),
+                };
+                origin.title = episode_title(&ep);
+                origin.meta.extend(episode_meta(&ep));
+                Ok(origin)
+            }
+        });
+        let origins = stream::iter(extractions)
+            .buffered(self.concurrency)
+            .try_collect()
+            .await?;
+        let title = data["result"]["season_title"]
+            .as_str()
+            .or_else(|| data["result"]["title"].as_str())
+            .unwrap_or_default()
+            .to_owned();
+        Ok(Playlist::new(origins, title))
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for Bangumi {
+    async fn extract(&mut self) -> crate::FinaResult {
+        if self.season {
+            return self.extract_season().await;
+        }
+        let page = self.current_page().await?;
+        let aid = match page["aid"].as_u64() {
+            Some(aid) => aid,
+            None => return err::InvalidResponse { resp: page }.fail(),
+        };
+        let cid = extract_cid(&page)?;
+        let mut base_extor = BaseExtractor::new(aid, cid, self.client.clone());
+        let (raws, title) = base_extor.extract().await?.into_parts();
+        let raws = raws
+            .into_iter()
+            .map(|mut origin| {
+                origin.title = episode_title(&page);
+                origin.meta.extend(episode_meta(&page));
+                origin
+            })
+            .collect();
+        Ok(Playlist::new(raws, title))
+    }
+}
+
+impl AsClient for Bangumi {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
+
+/// Turns responses rejected by risk control (e.g. missing or outdated WBI signatures)
+/// into `Error::RequestRejected`, and those requiring login into `Error::LoginRequired`
+fn check_code(data: Value) -> Result<Value, Error> {
+    match data["code"].as_i64() {
+        Some(-101) => err::LoginRequired { resp: data }.fail(),
+        Some(code @ (-352 | -403 | -412)) => err::RequestRejected {
+            code,
+            message: data["message"].as_str().unwrap_or_default(),
+        }
+        .fail(),
+        _ => Ok(data),
+    }
+}
+
+/// Whether `e` concerns a single item only, such as paid or removed videos,
+/// so that extraction of the rest of a list may go on.
+/// Errors about login or risk control affect every item, and are never ignored
+fn is_unavailable(e: &Error) -> bool {
+    matches!(
+        e,
+        Error::InvalidResponse { .. } | Error::PurchaseRequired { .. }
+    )
+}
+
+/// An item that cannot be extracted, kept without tracks to preserve the order of a list
+fn unavailable(title: String) -> Origin {
+    let mut meta = Meta::new();
+    meta.insert("unavailable".to_owned(), true.into());
+    Origin::new(Vec::new(), title).with_meta(meta)
+}
+
+fn extract_cid(data: &Value) -> Result<u64, Error> {
+    data["cid"]
+        .as_u64()
+        .ok_or_else(|| err::InvalidResponse { resp: data.clone() }.build())
+}
+
+/// Collects episodes of a season, main episodes first
+fn episodes(season: &Value, sections: bool) -> Vec<Value> {
+    let mut playlist = Vec::new();
+    if let Value::Array(ref eps) = season["episodes"] {
+        playlist.extend_from_slice(eps);
+    }
+    // only main episodes lie in season["episodes"]
+    if let (true, Value::Array(ref sections)) = (sections, &season["section"]) {
+        for section in sections {
+            if let Value::Array(ref eps) = section["episodes"] {
+                playlist.extend(eps.iter().map(|ep| {
+                    let mut ep = ep.clone();
+                    ep["section"] = section["title"].clone();
+                    ep
+                }));
+            }
+        }
+    }
+    playlist
+}
+
+/// Formats an episode title like `第3话 xxx`
+fn episode_title(ep: &Value) -> String {
+    let title = match ep["title"] {
+        Value::String(ref t) if t.parse::<f64>().is_ok() => format!("第{}话", t),
+        Value::String(ref t) => t.to_owned(),
+        _ => String::new(),
+    };
+    match ep["long_title"].as_str() {
+        Some(long_title) if !long_title.is_empty() && !title.is_empty() => {
+            format!("{} {}", title, long_title)
+        }
+        Some(long_title) if !long_title.is_empty() => long_title.to_owned(),
+        _ => title,
+    }
+}
+
+fn episode_meta(ep: &Value) -> Meta {
+    let mut meta = Meta::new();
+    meta.insert("ep_id".to_owned(), ep["id"].clone());
+    if let Some(num) = ep["title"].as_str().and_then(|t| t.parse::<u64>().ok()) {
+        meta.insert("episode".to_owned(), num.into());
+    }
+    if let Value::String(ref section) = ep["section"] {
+        meta.insert("section".to_owned(), section.clone().into());
+    }
+    let badge = ep["badge"]
+        .as_str()
+        .or_else(|| ep["badge_info"]["text"].as_str())
+        .unwrap_or_default();
+    if !badge.is_empty() {
+        meta.insert("badge".to_owned(), badge.into());
+    }
+    meta.insert("preview".to_owned(), badge.contains("预告").into());
+    // status 13 marks episodes for members only
+    let member_only = ep["status"].as_u64() == Some(13) || badge.contains("会员");
+    meta.insert("member_only".to_owned(), member_only.into());
+    meta
+}
+
+fn parse_dash(info: &Value) -> Result<Option<Origin>, Error> {
+    let streams = dash::streams(info)?;
+    let video = streams.iter().find(|s| s.is_video());
+    let audio = streams.iter().find(|s| s.is_audio());
+    if video.is_none() && audio.is_none() {
+        return Ok(None);
+    }
+    let mut meta = Meta::new();
+    for stream in video.iter().chain(audio.iter()) {
+        let (key, variant) = match stream.variant {
+            dash::Variant::Video(range) => ("dynamic_range", range.as_str()),
+            dash::Variant::Audio(format) => ("audio_format", format.as_str()),
+        };
+        meta.insert(key.to_owned(), variant.into());
+    }
+    meta.insert(
+        "streams".to_owned(),
+        streams.iter().map(dash::Stream::to_json).collect(),
+    );
+    let tracks = video
+        .into_iter()
+        .chain(audio)
+        .map(|s| s.track.clone())
+        .collect();
+    Ok(Some(Origin::new(tracks, String::new()).with_meta(meta)))
+}
+
+fn parse_durl(info: &Value) -> Result<Option<Origin>, Error> {
+    Ok(parse_durl_tracks(info)?.map(|tracks| Origin::new(tracks, String::new())))
+}
+
+fn parse_durl_tracks(info: &Value) -> Result<Option<Vec<Track>>, Error> {
+    Ok(match info.as_array() {
+        Some(urls) => {
+            let mut tracks = Vec::with_capacity(urls.len());
+            // need sorting by order probably
+            for i in urls {
+                match &i["url"] {
+                    Value::String(url) => tracks.push(Track::Video(url.parse()?)),
+                    _ => return Ok(None),
+                }
+            }
+            Some(tracks)
+        }
+        None => None,
+    })
+}
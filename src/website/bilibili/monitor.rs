@@ -0,0 +1,233 @@
+//! Watches live rooms and reports when they go live, go offline or change their titles
+use super::{check_code, Live};
+use crate::{error as err, utils::Client, Error};
+use futures_util::{stream::BoxStream, StreamExt};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+/// ?uids[]={}&uids[]={}
+pub const STATUS_API: &str = "https://api.live.bilibili.com/room/v1/Room/get_status_info_by_uids";
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(600);
+/// uids queried in one request
+const BATCH_SIZE: usize = 50;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct RoomStatus {
+    pub uid: u64,
+    pub room_id: u64,
+    #[serde(default)]
+    pub uname: String,
+    #[serde(default)]
+    pub title: String,
+    /// 0 for offline, 1 for live and 2 for replaying videos in turn
+    #[serde(default)]
+    pub live_status: u64,
+    /// unix timestamp when the live started, 0 if offline
+    #[serde(default)]
+    pub live_time: u64,
+    #[serde(default, rename = "cover_from_user")]
+    pub cover: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    WentLive(RoomStatus),
+    WentOffline(RoomStatus),
+    TitleChanged { old: String, status: RoomStatus },
+}
+
+/// Polls rooms and uids, see `Monitor::watch`
+pub struct Monitor {
+    client: Client,
+    uids: Vec<u64>,
+    /// rooms not resolved into uids yet
+    rooms: Vec<u64>,
+    /// rooms dropped as they cannot be resolved
+    invalid_rooms: Vec<u64>,
+    interval: Duration,
+    max_backoff: Duration,
+}
+
+impl RoomStatus {
+    pub fn is_live(&self) -> bool {
+        self.live_status == 1
+    }
+}
+
+impl Event {
+    /// Compares two statuses of the same room, `old` being `None` if it was not known before
+    pub fn between(old: Option<&RoomStatus>, new: &RoomStatus) -> Option<Self> {
+        match old {
+            None if new.is_live() => Some(Self::WentLive(new.clone())),
+            None => None,
+            Some(old) if !old.is_live() && new.is_live() => Some(Self::WentLive(new.clone())),
+            Some(old) if old.is_live() && !new.is_live() => Some(Self::WentOffline(new.clone())),
+            Some(old) if old.title != new.title => Some(Self::TitleChanged {
+                old: old.title.clone(),
+                status: new.clone(),
+            }),
+            Some(_) => None,
+        }
+    }
+    pub fn status(&self) -> &RoomStatus {
+        match self {
+            Self::WentLive(status) | Self::WentOffline(status) => status,
+            Self::TitleChanged { status, .. } => status,
+        }
+    }
+}
+
+impl Monitor {
+    pub fn new() -> Self {
+        Self::with_client(Client::new())
+    }
+    pub fn with_client(client: Client) -> Self {
+        Self {
+            client,
+            uids: Vec::new(),
+            rooms: Vec::new(),
+            invalid_rooms: Vec::new(),
+            interval: DEFAULT_INTERVAL,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+        }
+    }
+    pub fn uid(mut self, uid: u64) -> Self {
+        self.uids.push(uid);
+        self
+    }
+    pub fn uids(mut self, uids: impl IntoIterator<Item = u64>) -> Self {
+        self.uids.extend(uids);
+        self
+    }
+    /// Watches a room by its id, short ids are accepted as well
+    pub fn room(mut self, room: u64) -> Self {
+        self.rooms.push(room);
+        self
+    }
+    pub fn rooms(mut self, rooms: impl IntoIterator<Item = u64>) -> Self {
+        self.rooms.extend(rooms);
+        self
+    }
+    /// Time between two polls, 60s by default
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+    /// Upper bound of the delay after failed polls, which doubles on each failure. 600s by default
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Rooms dropped as they cannot be resolved into uids, such as nonexistent ones
+    pub fn invalid_rooms(&self) -> &[u64] {
+        &self.invalid_rooms
+    }
+
+    /// Resolves pending rooms into uids, returning errors of the rooms dropped.
+    /// Rooms failed for other reasons, such as network errors, are kept for the next poll
+    async fn resolve_rooms(&mut self) -> Vec<Error> {
+        let mut dropped = Vec::new();
+        let mut pending = Vec::new();
+        for room in std::mem::take(&mut self.rooms) {
+            match Live::with_client(self.client.clone(), room)
+                .room_info()
+                .await
+            {
+                Ok((_, uid, _)) => self.uids.push(uid),
+                Err(e @ Error::InvalidResponse { .. }) => {
+                    self.invalid_rooms.push(room);
+                    dropped.push(e);
+                }
+                Err(_) => pending.push(room),
+            }
+        }
+        self.rooms = pending;
+        dropped
+    }
+
+    /// Queries the status of all watched uids at once. Uids without a live room are left out,
+    /// and so are rooms that cannot be resolved, see `Monitor::invalid_rooms`
+    pub async fn poll(&mut self) -> Result<HashMap<u64, RoomStatus>, Error> {
+        Ok(self.poll_all().await?.0)
+    }
+
+    /// Like `poll`, also returning errors of the rooms dropped in this poll
+    async fn poll_all(&mut self) -> Result<(HashMap<u64, RoomStatus>, Vec<Error>), Error> {
+        let dropped = self.resolve_rooms().await;
+        self.uids.sort_unstable();
+        self.uids.dedup();
+        let mut statuses = HashMap::new();
+        for uids in self.uids.chunks(BATCH_SIZE) {
+            let query = uids
+                .iter()
+                .map(|uid| format!("uids[]={uid}"))
+                .collect::<Vec<_>>()
+                .join("&");
+            let url = format!("{STATUS_API}?{query}");
+            let data = check_code(self.client.send_json_request(url.parse()?).await?)?;
+            match data["data"] {
+                Value::Object(ref rooms) => {
+                    for room in rooms.values() {
+                        let status = RoomStatus::deserialize(room)
+                            .map_err(|_| err::InvalidResponse { resp: room.clone() }.build())?;
+                        statuses.insert(status.uid, status);
+                    }
+                }
+                // an empty array if none of the uids has a live room
+                Value::Array(ref rooms) if rooms.is_empty() => {}
+                _ => return err::InvalidResponse { resp: data }.fail(),
+            }
+        }
+        Ok((statuses, dropped))
+    }
+
+    /// Polls until the stream is dropped.
+    /// Rooms already live on the first poll are reported as `Event::WentLive`.
+    /// Failed polls are yielded as errors and retried with exponential backoff.
+    /// Rooms that cannot be resolved are yielded as errors once, and never polled again
+    pub fn watch(self) -> BoxStream<'static, Result<Event, Error>> {
+        let state = (self, HashMap::new(), VecDeque::new(), None);
+        futures_util::stream::unfold(state, |state| async move {
+            let (mut monitor, mut last, mut pending, mut delay) = state;
+            loop {
+                if let Some(event) = pending.pop_front() {
+                    return Some((event, (monitor, last, pending, delay)));
+                }
+                if let Some(delay) = delay {
+                    tokio::time::sleep(delay).await;
+                }
+                match monitor.poll_all().await {
+                    Ok((statuses, dropped)) => {
+                        pending.extend(dropped.into_iter().map(Err));
+                        pending.extend(
+                            statuses
+                                .values()
+                                .filter_map(|status| Event::between(last.get(&status.uid), status))
+                                .map(Ok),
+                        );
+                        last = statuses;
+                        delay = Some(monitor.interval);
+                    }
+                    Err(e) => {
+                        let backoff = delay.map_or(monitor.interval, |delay| delay * 2);
+                        delay = Some(backoff.min(monitor.max_backoff.max(monitor.interval)));
+                        return Some((Err(e), (monitor, last, pending, delay)));
+                    }
+                }
+            }
+        })
+        .boxed()
+    }
+}
+
+impl Default for Monitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,179 @@
+use super::{check_code, is_unavailable, wbi::SignWbi, Id, Video, HEADERS};
+use crate::{error as err, utils::Client, AsClient, Error, Extract, Origin, Playlist};
+use serde_json::Value;
+use url::Url;
+
+/// ?mid={}&ps={}&pn={}&order={}&keyword={}, signed with WBI
+pub const SPACE_ARC_API: &str = "https://api.bilibili.com/x/space/wbi/arc/search";
+/// ?mid={}
+pub const SPACE_CARD_API: &str = "https://api.bilibili.com/x/web-interface/card";
+const PAGE_SIZE: u64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Order {
+    /// newest first
+    #[default]
+    Pubdate,
+    /// most viewed first
+    Click,
+    /// most favorited first
+    Stow,
+}
+
+/// A video uploaded by a user, which can be extracted later
+#[derive(Debug, Clone, PartialEq)]
+pub struct Upload {
+    pub aid: u64,
+    pub bvid: String,
+    pub title: String,
+}
+
+/// A extractor for videos uploaded by a user (`space.bilibili.com/{mid}/video`)
+#[derive(Debug)]
+pub struct Space {
+    client: Client,
+    mid: u64,
+    order: Order,
+    keyword: String,
+}
+
+impl Order {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pubdate => "pubdate",
+            Self::Click => "click",
+            Self::Stow => "stow",
+        }
+    }
+}
+
+impl Upload {
+    pub(crate) fn from_json(v: &Value) -> Result<Self, Error> {
+        match (v["aid"].as_u64(), v["bvid"].as_str()) {
+            (Some(aid), Some(bvid)) => Ok(Self {
+                aid,
+                bvid: bvid.to_owned(),
+                title: v["title"].as_str().unwrap_or_default().to_owned(),
+            }),
+            _ => err::InvalidResponse { resp: v.clone() }.fail(),
+        }
+    }
+    pub fn extractor(&self, client: Client) -> Video {
+        Video::with_client(client, Id::Bv(self.bvid.clone()), None)
+    }
+    /// Extracts every page of the video
+    pub async fn origins(&self, client: Client) -> Result<Vec<Origin>, Error> {
+        self.extractor(client).pages().await
+    }
+}
+
+/// Extracts every page of `uploads`, ignoring videos that are unavailable, such as deleted ones
+pub(crate) async fn extract_uploads(
+    client: &Client,
+    uploads: &[Upload],
+) -> Result<Vec<Origin>, Error> {
+    let mut origins = Vec::new();
+    for upload in uploads {
+        match upload.origins(client.clone()).await {
+            Ok(pages) => origins.extend(pages),
+            Err(e) if is_unavailable(&e) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(origins)
+}
+
+impl Space {
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url = Url::parse(s)?;
+        url.path_segments()
+            .and_then(|mut it| it.next())
+            .and_then(|mid| mid.parse().ok())
+            .map(Self::with_mid)
+            .ok_or(Error::InvalidUrl { url })
+    }
+    pub fn with_mid(mid: u64) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), mid)
+    }
+    pub fn with_client(client: Client, mid: u64) -> Self {
+        Self {
+            client,
+            mid,
+            order: Order::default(),
+            keyword: String::new(),
+        }
+    }
+    pub fn order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+    /// Only extracts videos matching `keyword`
+    pub fn keyword(mut self, keyword: impl Into<String>) -> Self {
+        self.keyword = keyword.into();
+        self
+    }
+    /// Returns the name of the user
+    pub async fn name(&self) -> Result<String, Error> {
+        let url = Url::parse_with_params(SPACE_CARD_API, [("mid", self.mid.to_string())])?;
+        let data = check_code(self.client.send_json_request(url).await?)?;
+        match data["data"]["card"]["name"] {
+            Value::String(ref name) => Ok(name.to_owned()),
+            _ => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+    /// Returns uploads on page `pn` (starting from 1) and the total count of uploads
+    pub async fn page(&self, pn: u64) -> Result<(Vec<Upload>, u64), Error> {
+        let url = Url::parse_with_params(
+            SPACE_ARC_API,
+            [
+                ("mid", self.mid.to_string()),
+                ("ps", PAGE_SIZE.to_string()),
+                ("pn", pn.to_string()),
+                ("order", self.order.as_str().to_owned()),
+                ("keyword", self.keyword.clone()),
+            ],
+        )?;
+        let data = self.client.send_signed_json(&url).await?;
+        let vlist = match data["data"]["list"]["vlist"] {
+            Value::Array(ref vlist) => vlist,
+            _ => return err::InvalidResponse { resp: data }.fail(),
+        };
+        let uploads = vlist
+            .iter()
+            .map(Upload::from_json)
+            .collect::<Result<_, Error>>()?;
+        Ok((uploads, data["data"]["page"]["count"].as_u64().unwrap_or(0)))
+    }
+    /// Returns all uploads without extracting them
+    pub async fn uploads(&self) -> Result<Vec<Upload>, Error> {
+        let mut uploads = Vec::new();
+        for pn in 1.. {
+            let (page, count) = self.page(pn).await?;
+            let end = page.is_empty();
+            uploads.extend(page);
+            if end || uploads.len() as u64 >= count {
+                break;
+            }
+        }
+        Ok(uploads)
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for Space {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let title = self.name().await?;
+        let uploads = self.uploads().await?;
+        let origins = extract_uploads(&self.client, &uploads).await?;
+        Ok(Playlist::new(origins, title))
+    }
+}
+
+impl AsClient for Space {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
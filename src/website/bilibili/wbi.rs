@@ -0,0 +1,163 @@
+use super::check_code;
+use crate::{error as err, utils::Client, Error};
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use std::{
+    sync::RwLock,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use url::Url;
+
+/// returns `wbi_img` used for signing
+pub const NAV_API: &str = "https://api.bilibili.com/x/web-interface/nav";
+
+/// keys are rotated daily, so refreshing them hourly is enough
+const KEYS_TTL: Duration = Duration::from_secs(60 * 60);
+
+static KEYS: Lazy<RwLock<Option<(WbiKeys, Instant)>>> = Lazy::new(|| RwLock::new(None));
+
+const MIXIN_KEY_ENC_TAB: [usize; 64] = [
+    46, 47, 18, 2, 53, 8, 23, 32, 15, 50, 10, 31, 58, 3, 45, 35, 27, 43, 5, 49, 33, 9, 42, 19, 29,
+    28, 14, 39, 12, 38, 41, 13, 37, 48, 7, 16, 24, 55, 40, 61, 26, 17, 0, 1, 60, 51, 30, 4, 22, 25,
+    54, 21, 56, 59, 6, 63, 57, 62, 11, 36, 20, 34, 44, 52,
+];
+
+/// Keys used to sign requests to apis protected by WBI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WbiKeys {
+    img_key: String,
+    sub_key: String,
+}
+
+impl WbiKeys {
+    pub fn new(img_key: String, sub_key: String) -> Self {
+        Self { img_key, sub_key }
+    }
+    pub async fn fetch(client: &Client) -> Result<Self, Error> {
+        let data: Value = client.send_json_request(NAV_API.parse()?).await?;
+        let key = |url: &Value| {
+            url.as_str()
+                .and_then(|url| url.rsplit('/').next())
+                .and_then(|file| file.split('.').next())
+                .map(ToOwned::to_owned)
+        };
+        match (
+            key(&data["data"]["wbi_img"]["img_url"]),
+            key(&data["data"]["wbi_img"]["sub_url"]),
+        ) {
+            (Some(img_key), Some(sub_key)) => Ok(Self::new(img_key, sub_key)),
+            _ => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+    /// Returns keys cached for the process, fetching them if absent or outdated
+    pub async fn cached(client: &Client) -> Result<Self, Error> {
+        if let Some((keys, fetched)) = &*KEYS.read().unwrap() {
+            if fetched.elapsed() < KEYS_TTL {
+                return Ok(keys.clone());
+            }
+        }
+        let keys = Self::fetch(client).await?;
+        *KEYS.write().unwrap() = Some((keys.clone(), Instant::now()));
+        Ok(keys)
+    }
+    /// Drops cached keys, so that the next call to `cached` fetches them again
+    pub fn invalidate() {
+        *KEYS.write().unwrap() = None;
+    }
+    pub fn mixin_key(&self) -> String {
+        let raw = format!("{}{}", self.img_key, self.sub_key);
+        let raw = raw.as_bytes();
+        MIXIN_KEY_ENC_TAB
+            .iter()
+            .filter_map(|&i| raw.get(i).map(|&c| c as char))
+            .take(32)
+            .collect()
+    }
+    /// Signs `params` with the current time, returning the query string
+    pub fn sign<K: AsRef<str>, V: AsRef<str>>(&self, params: &[(K, V)]) -> String {
+        let wts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        self.sign_with_wts(params, wts)
+    }
+    /// Signs `params` with the given timestamp, returning the query string
+    pub fn sign_with_wts<K: AsRef<str>, V: AsRef<str>>(
+        &self,
+        params: &[(K, V)],
+        wts: u64,
+    ) -> String {
+        let mut params = params
+            .iter()
+            .map(|(k, v)| {
+                let v = v.as_ref().replace(['!', '\'', '(', ')', '*'], "");
+                (k.as_ref().to_owned(), v)
+            })
+            .chain(std::iter::once(("wts".to_owned(), wts.to_string())))
+            .collect::<Vec<_>>();
+        params.sort();
+        let query = params
+            .iter()
+            .map(|(k, v)| format!("{}={}", encode(k), encode(v)))
+            .collect::<Vec<_>>()
+            .join("&");
+        let w_rid = md5::compute(format!("{}{}", query, self.mixin_key()));
+        format!("{}&w_rid={:x}", query, w_rid)
+    }
+    /// Replaces the query of `url` with a signed one
+    pub fn sign_url(&self, url: &Url) -> Url {
+        let params = url
+            .query_pairs()
+            .filter(|(k, _)| k != "w_rid" && k != "wts")
+            .collect::<Vec<_>>();
+        let mut url = url.clone();
+        url.set_query(Some(&self.sign(&params)));
+        url
+    }
+}
+
+/// Signs `url` with keys cached for the process
+pub async fn sign_url(client: &Client, url: &Url) -> Result<Url, Error> {
+    Ok(WbiKeys::cached(client).await?.sign_url(url))
+}
+
+/// Lets requests to bilibili apis opt into WBI signing with keys cached for the process
+#[async_trait::async_trait]
+pub trait SignWbi {
+    /// Returns a GET request to `url` signed, which may be customized further before sending
+    async fn get_signed(&self, url: &Url) -> Result<reqwest::RequestBuilder, Error>;
+    /// Requests `url` signed, see `check_code` for errors.
+    /// If the signature is rejected, the request is retried once with keys fetched again
+    async fn send_signed_json(&self, url: &Url) -> Result<Value, Error>;
+}
+
+#[async_trait::async_trait]
+impl SignWbi for Client {
+    async fn get_signed(&self, url: &Url) -> Result<reqwest::RequestBuilder, Error> {
+        Ok(self.get(sign_url(self, url).await?))
+    }
+    async fn send_signed_json(&self, url: &Url) -> Result<Value, Error> {
+        let data = self.send_json_request(sign_url(self, url).await?).await?;
+        match check_code(data) {
+            // -403 of signed requests means outdated keys
+            Err(Error::RequestRejected { code: -403, .. }) => {
+                WbiKeys::invalidate();
+                let data = self.send_json_request(sign_url(self, url).await?).await?;
+                check_code(data)
+            }
+            res => res,
+        }
+    }
+}
+
+/// Percent-encodes everything except unreserved characters, with spaces as `%20`
+fn encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -9,18 +9,61 @@ pub trait Extractor: Extract + AsClient {}
 
 impl<T: Extract + AsClient> Extractor for T {}
 
+/// Chooses an extractor by the domain and path of `url`.
+/// Interactive videos share urls with plain ones and are extracted as their first segment,
+/// so `bilibili::Interactive` has to be built directly to extract every segment.
+/// Weibo live rooms are extracted as their replays, use `weibo::LiveStream` for the stream
 pub fn choose_extractor(url: &str) -> FinaResult<Box<dyn Extractor + 'static>> {
     let url = url::Url::parse(url)?;
     // todo: deal with unsupported url properly
     match url.domain() {
         Some("bilibili.com" | "www.bilibili.com") => {
-            if url.as_str().contains("av") || url.as_str().contains("BV") {
+            if url.path().starts_with("/opus/") {
+                Ok(Box::new(bilibili::Dynamic::new(url.as_str())?))
+            } else if url.path().starts_with("/read/cv") {
+                Ok(Box::new(bilibili::Article::new(url.as_str())?))
+            } else if url.path().starts_with("/cheese/") {
+                Ok(Box::new(bilibili::Course::new(url.as_str())?))
+            } else if url.path().starts_with("/audio/au") {
+                Ok(Box::new(bilibili::Audio::new(url.as_str())?))
+            } else if url.path().starts_with("/audio/am") {
+                Ok(Box::new(bilibili::AudioMenu::new(url.as_str())?))
+            } else if url.path().starts_with("/medialist/detail/ml") {
+                Ok(Box::new(bilibili::Favorites::new(url.as_str())?))
+            } else if url.path().contains("watchlater") {
+                Ok(Box::new(bilibili::WatchLater::new()))
+            } else if url.as_str().contains("av") || url.as_str().contains("BV") {
                 Ok(Box::new(bilibili::Video::new(url.as_str())?))
             } else {
                 Ok(Box::new(bilibili::Bangumi::new(url.as_str())?))
             }
         }
+        Some("space.bilibili.com") => {
+            let path = url.path();
+            let list_type = url
+                .query_pairs()
+                .find_map(|(k, v)| if k == "type" { Some(v) } else { None });
+            if path.contains("favlist") {
+                Ok(Box::new(bilibili::Favorites::new(url.as_str())?))
+            } else if path.contains("collectiondetail") || list_type.as_deref() == Some("season") {
+                Ok(Box::new(bilibili::UgcSeason::new(url.as_str())?))
+            } else if path.contains("seriesdetail") || list_type.as_deref() == Some("series") {
+                Ok(Box::new(bilibili::Series::new(url.as_str())?))
+            } else {
+                Ok(Box::new(bilibili::Space::new(url.as_str())?))
+            }
+        }
+        Some("t.bilibili.com") => Ok(Box::new(bilibili::Dynamic::new(url.as_str())?)),
+        Some("m.bilibili.com")
+            if url.path().starts_with("/dynamic/") || url.path().starts_with("/opus/") =>
+        {
+            Ok(Box::new(bilibili::Dynamic::new(url.as_str())?))
+        }
+        Some("manga.bilibili.com") => Ok(Box::new(bilibili::Manga::new(url.as_str())?)),
         Some("live.bilibili.com") => Ok(Box::new(bilibili::Live::new(url.as_str())?)),
+        Some("weibo.com" | "www.weibo.com") if url.path().starts_with("/l/wblive/") => {
+            Ok(Box::new(weibo::Live::new(url.as_str())?))
+        }
         Some("music.163.com") => {
             if url.as_str().contains("song") {
                 Ok(Box::new(netease_music::Song::new(url.as_str())?))
@@ -28,11 +71,17 @@ pub fn choose_extractor(url: &str) -> FinaResult<Box<dyn Extractor + 'static>> {
                 Ok(Box::new(netease_music::PlayList::new(url.as_str())?))
             }
         }
-        Some("pixiv.net" | "www.pixiv.net") => {
-            if url.as_str().contains("artworks") {
-                Ok(Box::new(pixiv::Pixiv::new(url.as_str())?))
-            } else {
-                Ok(Box::new(pixiv::Collection::new(url.as_str())?))
+        Some(domain) if domain.ends_with("pixiv.net") || domain.ends_with("pximg.net") => {
+            match pixiv::Link::parse(&url)? {
+                pixiv::Link::Artwork(pid) => Ok(Box::new(pixiv::Pixiv::with_pid(pid))),
+                pixiv::Link::User { .. } => Ok(Box::new(pixiv::User::new(url.as_str())?)),
+                pixiv::Link::Bookmarks { .. } => {
+                    Ok(Box::new(pixiv::Collection::new(url.as_str())?))
+                }
+                pixiv::Link::Series(id) => Ok(Box::new(pixiv::Series::with_id(id))),
+                pixiv::Link::Novel(id) => Ok(Box::new(pixiv::Novel::with_id(id))),
+                pixiv::Link::NovelSeries(id) => Ok(Box::new(pixiv::NovelSeries::with_id(id))),
+                pixiv::Link::Ranking => Ok(Box::new(pixiv::Ranking::new(url.as_str())?)),
             }
         }
         _ => Err(Error::InvalidUrl { url }),
@@ -0,0 +1,511 @@
+//! Extraction through the app api, authenticated with OAuth refresh tokens.
+//! Refresh tokens last much longer than web cookies, which suits unattended jobs
+use super::{is_unavailable, ugoira, unavailable, Restriction, Size};
+use crate::{error as err, utils::Client, AsClient, Error, Extract, Meta, Origin, Playlist, Track};
+use once_cell::sync::Lazy;
+use reqwest::header::{self, HeaderMap, HeaderName};
+use serde_json::Value;
+use snafu::ResultExt;
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use url::Url;
+
+/// post client_id, client_secret, grant_type=refresh_token and refresh_token as form
+pub const AUTH_TOKEN_URL: &str = "https://oauth.secure.pixiv.net/auth/token";
+/// v1/illust/detail?illust_id={}, v1/ugoira/metadata?illust_id={},
+/// v1/user/bookmarks/illust?user_id={}&restrict={public|private}, v1/user/detail?user_id={}
+pub const APP_API: &str = "https://app-api.pixiv.net/";
+/// credentials of the android app
+const CLIENT_ID: &str = "MOBrBDS8blbauoSck0ZfDbtuzpyT";
+const CLIENT_SECRET: &str = "lsACyCD94FhDUtGTXi3QzcFE2uU1hqtDaKeqrdwj";
+/// access tokens are refreshed this long before they expire
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+static APP_HEADERS: Lazy<HeaderMap> = Lazy::new(|| {
+    crate::hdmap! {
+        header::USER_AGENT => "PixivAndroidApp/5.0.234 (Android 11; Pixel 5)",
+        header::REFERER => "https://app-api.pixiv.net/",
+        HeaderName::from_static("app-os") => "android",
+        HeaderName::from_static("app-os-version") => "11",
+    }
+});
+
+/// OAuth credentials shared by app api extractors. Access tokens are refreshed automatically
+/// before they expire, and the refresh token returned by pixiv replaces the one passed.
+/// Clones share the same tokens
+#[derive(Debug, Clone)]
+pub struct Auth {
+    token_url: Url,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug)]
+struct State {
+    refresh_token: String,
+    access_token: Option<(String, Instant)>,
+}
+
+/// Parses the response of `AUTH_TOKEN_URL` into access token, refresh token and seconds until expiry
+pub fn parse_token(data: &Value) -> Result<(String, String, u64), Error> {
+    // older responses wrap tokens in `response`
+    let token = match data["response"] {
+        Value::Object(_) => &data["response"],
+        _ => data,
+    };
+    match (
+        token["access_token"].as_str(),
+        token["refresh_token"].as_str(),
+        token["expires_in"].as_u64(),
+    ) {
+        (Some(access), Some(refresh), Some(expires_in)) => {
+            Ok((access.to_owned(), refresh.to_owned(), expires_in))
+        }
+        // invalid or revoked refresh tokens
+        _ if data["has_error"].as_bool() == Some(true) || data["errors"].is_object() => {
+            err::LoginRequired { resp: data.clone() }.fail()
+        }
+        _ => err::InvalidResponse { resp: data.clone() }.fail(),
+    }
+}
+
+impl Auth {
+    pub fn new(refresh_token: impl Into<String>) -> Self {
+        Self {
+            token_url: AUTH_TOKEN_URL.parse().unwrap(),
+            state: Arc::new(Mutex::new(State {
+                refresh_token: refresh_token.into(),
+                access_token: None,
+            })),
+        }
+    }
+    /// Replaces `AUTH_TOKEN_URL`
+    pub fn token_url(mut self, url: Url) -> Self {
+        self.token_url = url;
+        self
+    }
+    /// Returns the latest refresh token, which should be saved for later runs
+    pub fn refresh_token(&self) -> String {
+        self.state.lock().unwrap().refresh_token.clone()
+    }
+    /// Exchanges the refresh token for a new access token
+    pub async fn refresh(&self, client: &Client) -> Result<String, Error> {
+        let refresh_token = self.refresh_token();
+        let form = [
+            ("client_id", CLIENT_ID),
+            ("client_secret", CLIENT_SECRET),
+            ("grant_type", "refresh_token"),
+            ("include_policy", "true"),
+            ("refresh_token", refresh_token.as_str()),
+        ];
+        let data = client.post_form_json(self.token_url.clone(), &form).await?;
+        let (access, refresh, expires_in) = parse_token(&data)?;
+        let expiry = Instant::now() + Duration::from_secs(expires_in);
+        let mut state = self.state.lock().unwrap();
+        state.refresh_token = refresh;
+        state.access_token = Some((access.clone(), expiry));
+        Ok(access)
+    }
+    /// Returns the access token, refreshing it when missing or about to expire
+    pub async fn access_token(&self, client: &Client) -> Result<String, Error> {
+        let cached = self
+            .state
+            .lock()
+            .unwrap()
+            .access_token
+            .clone()
+            .filter(|(_, expiry)| Instant::now() + EXPIRY_MARGIN < *expiry);
+        match cached {
+            Some((token, _)) => Ok(token),
+            None => self.refresh(client).await,
+        }
+    }
+    async fn send(&self, client: &Client, url: &Url, token: &str) -> Result<Value, Error> {
+        Ok(client
+            .get(url.clone())
+            .bearer_auth(token)
+            .send()
+            .await
+            .context(err::NetworkError { url: url.clone() })?
+            .json()
+            .await?)
+    }
+    /// Sends an authorized GET request to the app api,
+    /// refreshing the access token once if it is rejected
+    pub async fn get_json(&self, client: &Client, url: Url) -> Result<Value, Error> {
+        let token = self.access_token(client).await?;
+        let data = self.send(client, &url, &token).await?;
+        if !data["error"].is_object() {
+            return Ok(data);
+        }
+        if !is_oauth_error(&data) {
+            return err::InvalidResponse { resp: data }.fail();
+        }
+        // access tokens may be revoked before they expire
+        let token = self.refresh(client).await?;
+        let data = self.send(client, &url, &token).await?;
+        match data["error"] {
+            Value::Object(_) if is_oauth_error(&data) => err::LoginRequired { resp: data }.fail(),
+            Value::Object(_) => err::InvalidResponse { resp: data }.fail(),
+            _ => Ok(data),
+        }
+    }
+}
+
+/// Whether the app api rejects the access token, rather than failing for other reasons
+/// such as works not found
+fn is_oauth_error(data: &Value) -> bool {
+    let message = data["error"]["message"].as_str().unwrap_or_default();
+    message.contains("invalid_grant") || message.contains("Error occurred at the OAuth process")
+}
+
+/// Collects metadata from an illust of the app api, with the same keys as `artwork_meta`.
+/// `upload_date` and `like_count` are always `null` as the app api does not provide them
+pub fn app_meta(illust: &Value) -> Meta {
+    let tags = illust["tags"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|tag| {
+            let mut item = Meta::new();
+            item.insert("tag".to_owned(), tag["name"].as_str()?.into());
+            if let Some(translation) = tag["translated_name"].as_str() {
+                item.insert("translation".to_owned(), translation.into());
+            }
+            Some(Value::Object(item))
+        })
+        .collect::<Vec<_>>();
+    let restriction = illust["x_restrict"]
+        .as_u64()
+        .and_then(Restriction::from_x_restrict)
+        .map(|r| r.as_str());
+    let ai_generated = match illust["illust_ai_type"].as_u64() {
+        Some(1) => Some(false),
+        Some(2) => Some(true),
+        _ => None,
+    };
+    let illust_type = match illust["type"].as_str() {
+        Some("illust") => Some(0),
+        Some("manga") => Some(1),
+        Some("ugoira") => Some(2),
+        _ => None,
+    };
+    let mut meta = Meta::new();
+    meta.insert(
+        "pid".to_owned(),
+        illust["id"].as_u64().map(|id| id.to_string()).into(),
+    );
+    meta.insert("illust_type".to_owned(), illust_type.into());
+    meta.insert(
+        "user_id".to_owned(),
+        illust["user"]["id"]
+            .as_u64()
+            .map(|id| id.to_string())
+            .into(),
+    );
+    meta.insert("user_name".to_owned(), illust["user"]["name"].clone());
+    meta.insert("tags".to_owned(), tags.into());
+    meta.insert("restriction".to_owned(), restriction.into());
+    meta.insert("ai_generated".to_owned(), ai_generated.into());
+    meta.insert("create_date".to_owned(), illust["create_date"].clone());
+    meta.insert("upload_date".to_owned(), Value::Null);
+    meta.insert("page_count".to_owned(), illust["page_count"].clone());
+    meta.insert(
+        "bookmark_count".to_owned(),
+        illust["total_bookmarks"].clone(),
+    );
+    meta.insert("like_count".to_owned(), Value::Null);
+    meta.insert("view_count".to_owned(), illust["total_view"].clone());
+    meta.insert("description".to_owned(), illust["caption"].clone());
+    meta
+}
+
+/// Picks urls of images of `size` from an illust of the app api.
+/// Thumbnails are `square_medium`, the smallest ones of the app api
+pub fn app_images(illust: &Value, size: Size) -> Result<Vec<Url>, Error> {
+    let key = match size {
+        Size::Original => "original",
+        Size::Regular => "large",
+        Size::Small => "medium",
+        Size::ThumbMini => "square_medium",
+    };
+    let urls = match illust["meta_pages"] {
+        Value::Array(ref pages) if !pages.is_empty() => pages
+            .iter()
+            .map(|page| page["image_urls"][key].as_str())
+            .collect::<Option<Vec<_>>>(),
+        // single page works
+        _ if size == Size::Original => illust["meta_single_page"]["original_image_url"]
+            .as_str()
+            .map(|url| vec![url]),
+        _ => illust["image_urls"][key].as_str().map(|url| vec![url]),
+    };
+    match urls {
+        Some(urls) => urls.into_iter().map(|url| Ok(url.parse()?)).collect(),
+        None => err::InvalidResponse {
+            resp: illust.clone(),
+        }
+        .fail(),
+    }
+}
+
+/// Returns dimensions of an image of `width`x`height` picked by `app_images`,
+/// whose sizes differ from the ones of the web api
+fn app_fit(size: Size, width: u64, height: u64) -> (u64, u64) {
+    let (max_width, max_height) = match size {
+        Size::Original => return (width, height),
+        Size::Regular => (600, 1200),
+        Size::Small => (540, 540),
+        Size::ThumbMini => return (360, 360),
+    };
+    if width <= max_width && height <= max_height {
+        (width, height)
+    } else if width * max_height > height * max_width {
+        (max_width, height * max_width / width)
+    } else {
+        (width * max_height / height, max_height)
+    }
+}
+
+/// Shared by app api extractors
+async fn illust_origin(
+    client: &Client,
+    auth: &Auth,
+    api: &Url,
+    illust: &Value,
+    size: Size,
+) -> Result<Origin, Error> {
+    let title = illust["title"].as_str().unwrap_or_default().to_owned();
+    let mut meta = app_meta(illust);
+    if illust["type"].as_str() == Some("ugoira") {
+        let url = Url::parse_with_params(
+            api.join("v1/ugoira/metadata")?.as_str(),
+            [("illust_id", illust["id"].to_string())],
+        )?;
+        let data = auth.get_json(client, url).await?;
+        let metadata = &data["ugoira_metadata"];
+        // zips of frames fitting in 1920x1080 are provided for some works only
+        let (key, frame_size) = match size {
+            Size::Original if metadata["zip_urls"]["1920x1080"].is_string() => {
+                ("1920x1080", "1920x1080")
+            }
+            _ => ("medium", "600x600"),
+        };
+        let src = match metadata["zip_urls"][key].as_str() {
+            Some(src) => src,
+            None => return err::InvalidResponse { resp: data }.fail(),
+        };
+        // the app api names frames by their format instead of giving `mime_type`
+        let mime_type = match metadata["frames"][0]["file"].as_str() {
+            Some(file) if file.ends_with(".png") => "image/png",
+            Some(file) if file.ends_with(".gif") => "image/gif",
+            Some(file) if file.ends_with(".jpg") || file.ends_with(".jpeg") => "image/jpeg",
+            _ => "",
+        };
+        let ugoira = ugoira::UgoiraMeta::from_json(&serde_json::json!({
+            "originalSrc": src,
+            "mime_type": mime_type,
+            "frames": metadata["frames"],
+        }))?;
+        let mut ugoira_meta = ugoira.to_json();
+        ugoira_meta["size"] = frame_size.into();
+        meta.insert("ugoira".to_owned(), ugoira_meta);
+        return Ok(Origin::new(vec![Track::Image(ugoira.src)], title).with_meta(meta));
+    }
+    let urls = app_images(illust, size)?;
+    // the app api provides dimensions of the first page only
+    let first = match (illust["width"].as_u64(), illust["height"].as_u64()) {
+        (Some(width), Some(height)) => Some(app_fit(size, width, height)),
+        _ => None,
+    };
+    let images = (0..urls.len())
+        .map(|i| {
+            let (width, height) = first.filter(|_| i == 0).unzip();
+            serde_json::json!({
+                "size": size.as_str(),
+                "width": width,
+                "height": height,
+            })
+        })
+        .collect::<Vec<_>>();
+    let tracks = urls.into_iter().map(Track::Image).collect();
+    meta.insert("size".to_owned(), size.as_str().into());
+    meta.insert("images".to_owned(), images.into());
+    Ok(Origin::new(tracks, title).with_meta(meta))
+}
+
+/// A extractor for a work through the app api
+#[derive(Debug)]
+pub struct AppPixiv {
+    client: Client,
+    auth: Auth,
+    api: Url,
+    pid: String,
+    size: Size,
+}
+
+impl AppPixiv {
+    pub fn new(auth: Auth, pid: String) -> Self {
+        Self::with_client(Client::with_header(APP_HEADERS.clone()), auth, pid)
+    }
+    pub fn with_client(client: Client, auth: Auth, pid: String) -> Self {
+        Self {
+            client,
+            auth,
+            api: APP_API.parse().unwrap(),
+            pid,
+            size: Size::default(),
+        }
+    }
+    /// Replaces `APP_API`
+    pub fn api(mut self, api: Url) -> Self {
+        self.api = api;
+        self
+    }
+    /// Size of images to extract, the original ones by default.
+    /// The size and dimensions of each image are put into the meta as `images`,
+    /// which are known only for the first page as the app api provides no others.
+    /// Frames of ugoira are 600x600 unless 1920x1080 ones are provided and the original size
+    /// is requested, whose bound is put into the meta as `ugoira.size`
+    pub fn size(mut self, size: Size) -> Self {
+        self.size = size;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for AppPixiv {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let url = Url::parse_with_params(
+            self.api.join("v1/illust/detail")?.as_str(),
+            [("illust_id", self.pid.as_str())],
+        )?;
+        let data = self.auth.get_json(&self.client, url).await?;
+        if !data["illust"].is_object() {
+            return err::InvalidResponse { resp: data }.fail();
+        }
+        let origin = illust_origin(
+            &self.client,
+            &self.auth,
+            &self.api,
+            &data["illust"],
+            self.size,
+        )
+        .await?;
+        let title = origin.title.clone();
+        Ok(Playlist::new(vec![origin], title))
+    }
+}
+
+impl AsClient for AppPixiv {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
+
+/// A extractor for bookmarks of a user through the app api, titled with the name of the user.
+/// Works that cannot be extracted, such as deleted ones, are kept without tracks
+/// and marked `unavailable` in the meta along with their `pid`
+#[derive(Debug)]
+pub struct AppBookmarks {
+    client: Client,
+    auth: Auth,
+    api: Url,
+    uid: String,
+    private: bool,
+    size: Size,
+}
+
+impl AppBookmarks {
+    pub fn new(auth: Auth, uid: String) -> Self {
+        Self::with_client(Client::with_header(APP_HEADERS.clone()), auth, uid)
+    }
+    pub fn with_client(client: Client, auth: Auth, uid: String) -> Self {
+        Self {
+            client,
+            auth,
+            api: APP_API.parse().unwrap(),
+            uid,
+            private: false,
+            size: Size::default(),
+        }
+    }
+    /// Replaces `APP_API`
+    pub fn api(mut self, api: Url) -> Self {
+        self.api = api;
+        self
+    }
+    /// Extracts private bookmarks, which are visible only to their owner
+    pub fn private(mut self) -> Self {
+        self.private = true;
+        self
+    }
+    /// Size of images to extract, the original ones by default, see `AppPixiv::size`
+    pub fn size(mut self, size: Size) -> Self {
+        self.size = size;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for AppBookmarks {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let restrict = if self.private { "private" } else { "public" };
+        let mut url = Url::parse_with_params(
+            self.api.join("v1/user/bookmarks/illust")?.as_str(),
+            [("user_id", self.uid.as_str()), ("restrict", restrict)],
+        )?;
+        let user = Url::parse_with_params(
+            self.api.join("v1/user/detail")?.as_str(),
+            [("user_id", self.uid.as_str())],
+        )?;
+        let user = self.auth.get_json(&self.client, user).await?;
+        let title = user["user"]["name"].as_str().unwrap_or_default().to_owned();
+        let mut origins = Vec::new();
+        loop {
+            let data = self.auth.get_json(&self.client, url).await?;
+            let illusts = match data["illusts"] {
+                Value::Array(ref illusts) => illusts,
+                _ => return err::InvalidResponse { resp: data }.fail(),
+            };
+            for illust in illusts {
+                match illust_origin(&self.client, &self.auth, &self.api, illust, self.size).await {
+                    Ok(origin) => origins.push(origin),
+                    Err(e) if is_unavailable(&e) => {
+                        let title = illust["title"].as_str().unwrap_or_default().to_owned();
+                        let mut origin = unavailable(title);
+                        origin.meta.insert(
+                            "pid".to_owned(),
+                            illust["id"].as_u64().map(|id| id.to_string()).into(),
+                        );
+                        origins.push(origin);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            // `next_url` points to the app api, so it is rebased for replaced apis
+            url = match data["next_url"].as_str().map(Url::parse) {
+                Some(Ok(next)) => {
+                    let mut rebased = self.api.join(next.path().trim_start_matches('/'))?;
+                    rebased.set_query(next.query());
+                    rebased
+                }
+                _ => break,
+            };
+        }
+        Ok(Playlist::new(origins, title))
+    }
+}
+
+impl AsClient for AppBookmarks {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
@@ -0,0 +1,115 @@
+//! Parsing of the urls of pixiv seen in the wild, including legacy ones and direct image links
+use super::WorkType;
+use crate::Error;
+use url::Url;
+
+/// What a pixiv url points to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Link {
+    /// `/artworks/{pid}`, `/i/{pid}`, `member_illust.php?illust_id={pid}` or `i.pximg.net/.../{pid}_p0.png`
+    Artwork(String),
+    /// `/users/{uid}`, `member.php?id={uid}` or `member_illust.php?id={uid}`,
+    /// with kinds of works in the section linked, empty for all works
+    User { uid: String, kinds: Vec<WorkType> },
+    /// `/users/{uid}/bookmarks/artworks` or `bookmark.php?id={uid}`, private with `rest=hide`
+    Bookmarks { uid: String, private: bool },
+    /// `/user/{uid}/series/{id}`
+    Series(String),
+    /// `/novel/show.php?id={id}`
+    Novel(String),
+    /// `/novel/series/{id}`
+    NovelSeries(String),
+    /// `/ranking.php`, whose parameters are parsed by `Ranking::new`
+    Ranking,
+}
+
+fn numeric(s: &str) -> Option<String> {
+    Some(s)
+        .filter(|s| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()))
+        .map(ToOwned::to_owned)
+}
+
+/// Directories on `i.pximg.net` holding pages, thumbnails and ugoira zips of works
+const ARTWORK_DIRS: [&str; 4] = [
+    "img-original",
+    "img-master",
+    "custom-thumb",
+    "img-zip-ugoira",
+];
+
+/// Language prefixes such as `en` in `/en/artworks/{pid}`
+fn is_language(segment: &str) -> bool {
+    let mut parts = segment.split('-');
+    let lang = parts.next().unwrap_or_default();
+    lang.len() == 2
+        && lang.bytes().all(|b| b.is_ascii_lowercase())
+        && parts.all(|region| region.len() == 2 && region.bytes().all(|b| b.is_ascii_alphabetic()))
+}
+
+impl Link {
+    pub fn parse(url: &Url) -> Result<Self, Error> {
+        Self::classify(url).ok_or_else(|| Error::InvalidUrl { url: url.clone() })
+    }
+    fn classify(url: &Url) -> Option<Self> {
+        let host = url.host_str()?;
+        let query = |key: &str| {
+            url.query_pairs()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.into_owned())
+        };
+        let segments = url
+            .path_segments()?
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        if host == "pximg.net" || host.ends_with(".pximg.net") {
+            // other files, such as avatars in `user-profile`, are not works
+            if !segments.iter().any(|s| ARTWORK_DIRS.contains(s)) {
+                return None;
+            }
+            // file names of pages, thumbnails and ugoira zips start with `{pid}_`
+            let name = segments.last()?;
+            return numeric(name.split(['_', '.']).next()?).map(Self::Artwork);
+        }
+        if host != "pixiv.net" && !host.ends_with(".pixiv.net") {
+            return None;
+        }
+        let segments = match segments.split_first() {
+            Some((first, rest)) if is_language(first) => rest,
+            _ => &segments[..],
+        };
+        let private = query("rest").as_deref() == Some("hide");
+        match segments {
+            ["artworks", pid, ..] | ["i", pid] => numeric(pid).map(Self::Artwork),
+            ["member_illust.php"] => match query("illust_id") {
+                Some(pid) => numeric(&pid).map(Self::Artwork),
+                None => numeric(&query("id")?).map(|uid| Self::User {
+                    uid,
+                    kinds: Vec::new(),
+                }),
+            },
+            ["member.php"] => numeric(&query("id")?).map(|uid| Self::User {
+                uid,
+                kinds: Vec::new(),
+            }),
+            ["bookmark.php"] => numeric(&query("id")?).map(|uid| Self::Bookmarks { uid, private }),
+            // bookmarked novels are not supported
+            ["users", uid, "bookmarks"] | ["users", uid, "bookmarks", "artworks"] => {
+                numeric(uid).map(|uid| Self::Bookmarks { uid, private })
+            }
+            ["users", uid, rest @ ..] => {
+                let kinds = match rest.first() {
+                    Some(&"illustrations") => vec![WorkType::Illust, WorkType::Ugoira],
+                    Some(&"manga") => vec![WorkType::Manga],
+                    None | Some(&"artworks") => Vec::new(),
+                    Some(_) => return None,
+                };
+                numeric(uid).map(|uid| Self::User { uid, kinds })
+            }
+            ["user", _, "series", id] => numeric(id).map(Self::Series),
+            ["novel", "show.php"] => numeric(&query("id")?).map(Self::Novel),
+            ["novel", "series", id] => numeric(id).map(Self::NovelSeries),
+            ["ranking.php"] => Some(Self::Ranking),
+            _ => None,
+        }
+    }
+}
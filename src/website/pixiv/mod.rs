@@ -0,0 +1,519 @@
+pub mod app;
+pub mod link;
+pub mod novel;
+pub mod ranking;
+pub mod series;
+pub mod ugoira;
+pub mod user;
+
+pub use app::{AppBookmarks, AppPixiv, Auth};
+pub use link::Link;
+pub use novel::{Novel, NovelSeries};
+pub use ranking::Ranking;
+pub use series::Series;
+pub use user::{User, WorkType};
+
+use crate::{
+    error as err, utils, utils::Client, AsClient, Error, Extract, Meta, Origin, Playlist, Track,
+};
+use futures_util::{stream, StreamExt};
+use once_cell::sync::Lazy;
+use reqwest::{header, header::HeaderMap};
+use serde_json::Value;
+use snafu::ResultExt;
+use url::Url;
+
+const LIMIT: u64 = 96;
+const CONCURRENCY: usize = 4;
+/// {uid}/illusts/bookmarks?tag={}&offset={}&limit={}&rest={show|hide}
+pub const BOOKMARKS_API: &str = "https://www.pixiv.net/ajax/user/";
+/// relative to `BOOKMARKS_API`, fails when not logged in
+const LOGIN_CHECK_PATH: &str = "extra";
+
+static HEADERS: Lazy<HeaderMap> = Lazy::new(|| {
+    crate::hdmap! {
+        header::USER_AGENT => utils::UA.clone(),
+        header::REFERER => "https://www.pixiv.net/",
+    }
+});
+static IMAGE_API: Lazy<Url> =
+    Lazy::new(|| Url::parse("https://www.pixiv.net/ajax/illust/").unwrap());
+
+/// Downloads a file from `i.pximg.net`, which requires the referer of pixiv
+async fn download(client: &Client, url: Url) -> Result<Vec<u8>, Error> {
+    let bytes = client
+        .get(url.clone())
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .context(err::NetworkError { url: url.clone() })?
+        .bytes()
+        .await
+        .context(err::NetworkError { url })?;
+    Ok(bytes.to_vec())
+}
+
+/// Whether `e` concerns a single work only, such as deleted or private ones,
+/// so that extraction of the rest of a list may go on.
+/// Errors about login or network affect every work, and are never ignored
+fn is_unavailable(e: &Error) -> bool {
+    matches!(e, Error::InvalidResponse { .. })
+}
+
+/// A work that cannot be extracted, such as a deleted one,
+/// kept without tracks to preserve the order of a series
+fn unavailable(title: String) -> Origin {
+    let mut meta = Meta::new();
+    meta.insert("unavailable".to_owned(), true.into());
+    Origin::new(Vec::new(), title).with_meta(meta)
+}
+
+/// Size variants of images
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Size {
+    #[default]
+    Original,
+    /// fits in 1200x1200
+    Regular,
+    /// fits in 540x540
+    Small,
+    /// 128x128, cropped
+    ThumbMini,
+}
+
+impl Size {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Original => "original",
+            Self::Regular => "regular",
+            Self::Small => "small",
+            Self::ThumbMini => "thumb_mini",
+        }
+    }
+    /// Returns dimensions of an image of `width`x`height` in this size
+    pub fn fit(&self, width: u64, height: u64) -> (u64, u64) {
+        let bound = match self {
+            Self::Original => return (width, height),
+            Self::Regular => 1200,
+            Self::Small => 540,
+            Self::ThumbMini => return (128, 128),
+        };
+        let longer = width.max(height);
+        if longer <= bound {
+            (width, height)
+        } else {
+            (width * bound / longer, height * bound / longer)
+        }
+    }
+}
+
+/// Picks the url of `size` from an item of the pages api,
+/// along with the size and dimensions of the image
+pub fn page_image(page: &Value, size: Size) -> Result<(Url, Value), Error> {
+    let url = match page["urls"][size.as_str()] {
+        Value::String(ref url) => Url::parse(url)?,
+        _ => return err::InvalidResponse { resp: page.clone() }.fail(),
+    };
+    let (width, height) = size.fit(
+        page["width"].as_u64().unwrap_or_default(),
+        page["height"].as_u64().unwrap_or_default(),
+    );
+    let image = serde_json::json!({
+        "size": size.as_str(),
+        "width": width,
+        "height": height,
+    });
+    Ok((url, image))
+}
+
+/// Age restriction of a work, from `xRestrict`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Restriction {
+    General,
+    R18,
+    /// grotesque
+    R18G,
+}
+
+impl Restriction {
+    pub fn from_x_restrict(x_restrict: u64) -> Option<Self> {
+        match x_restrict {
+            0 => Some(Self::General),
+            1 => Some(Self::R18),
+            2 => Some(Self::R18G),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::General => "general",
+            Self::R18 => "R-18",
+            Self::R18G => "R-18G",
+        }
+    }
+}
+
+/// Collects metadata of a work from `body` of the illust api:
+/// `pid`, `illust_type`, `user_id`, `user_name`, `tags` (with `translation` when available),
+/// `restriction`, `ai_generated` (`null` when unknown), `create_date`, `upload_date`, `page_count`,
+/// `bookmark_count`, `like_count`, `view_count` and `description` (html)
+pub fn artwork_meta(body: &Value) -> Meta {
+    let tags = body["tags"]["tags"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|tag| {
+            let mut item = Meta::new();
+            item.insert("tag".to_owned(), tag["tag"].as_str()?.into());
+            if let Some(translation) = tag["translation"]["en"].as_str() {
+                item.insert("translation".to_owned(), translation.into());
+            }
+            Some(Value::Object(item))
+        })
+        .collect::<Vec<_>>();
+    let restriction = body["xRestrict"]
+        .as_u64()
+        .and_then(Restriction::from_x_restrict)
+        .map(|r| r.as_str());
+    // 0 for works uploaded before the field exists, 1 for no, 2 for yes
+    let ai_generated = match body["aiType"].as_u64() {
+        Some(1) => Some(false),
+        Some(2) => Some(true),
+        _ => None,
+    };
+    let mut meta = Meta::new();
+    meta.insert("pid".to_owned(), body["illustId"].clone());
+    meta.insert("illust_type".to_owned(), body["illustType"].clone());
+    meta.insert("user_id".to_owned(), body["userId"].clone());
+    meta.insert("user_name".to_owned(), body["userName"].clone());
+    meta.insert("tags".to_owned(), tags.into());
+    meta.insert("restriction".to_owned(), restriction.into());
+    meta.insert("ai_generated".to_owned(), ai_generated.into());
+    meta.insert("create_date".to_owned(), body["createDate"].clone());
+    meta.insert("upload_date".to_owned(), body["uploadDate"].clone());
+    meta.insert("page_count".to_owned(), body["pageCount"].clone());
+    meta.insert("bookmark_count".to_owned(), body["bookmarkCount"].clone());
+    meta.insert("like_count".to_owned(), body["likeCount"].clone());
+    meta.insert("view_count".to_owned(), body["viewCount"].clone());
+    meta.insert("description".to_owned(), body["description"].clone());
+    meta
+}
+
+#[derive(Debug)]
+pub struct Pixiv {
+    client: Client,
+    pid: String,
+    size: Size,
+}
+
+impl Pixiv {
+    /// Accepts any url of the work, see `Link`
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url = Url::parse(s)?;
+        match Link::parse(&url)? {
+            Link::Artwork(pid) => Ok(Self::with_pid(pid)),
+            _ => Err(Error::InvalidUrl { url }),
+        }
+    }
+    pub fn with_pid(pid: String) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), pid)
+    }
+    pub fn with_client(client: Client, pid: String) -> Self {
+        Self {
+            client,
+            pid,
+            size: Size::default(),
+        }
+    }
+    /// Size of images to extract, the original ones by default.
+    /// The size and dimensions of each image are put into the meta as `images`
+    pub fn size(mut self, size: Size) -> Self {
+        self.size = size;
+        self
+    }
+    async fn raw_url_json(&self) -> Result<Value, Error> {
+        let url = IMAGE_API.join(&format!("{}/pages", self.pid)).unwrap();
+        let data = self.client.send_json_request(url).await?;
+        Ok(data)
+    }
+    async fn meta_json(&self) -> Result<Value, Error> {
+        let url = IMAGE_API.join(&self.pid).unwrap();
+        let data = self.client.send_json_request(url).await?;
+        Ok(data)
+    }
+    async fn raw_urls(&self) -> Result<Vec<Value>, Error> {
+        let mut data = self.raw_url_json().await?;
+        match data["body"] {
+            Value::Array(ref mut urls) => Ok(urls.to_owned()),
+            _ => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+    /// Returns frame zip and timing of a ugoira work
+    pub async fn ugoira_meta(&self) -> Result<ugoira::UgoiraMeta, Error> {
+        let url = IMAGE_API
+            .join(&format!("{}/ugoira_meta", self.pid))
+            .unwrap();
+        let data: Value = self.client.send_json_request(url).await?;
+        ugoira::UgoiraMeta::from_json(&data["body"])
+            .map_err(|_| err::InvalidResponse { resp: data }.build())
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for Pixiv {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let data = self.meta_json().await?;
+        let title = match data["body"]["title"] {
+            Value::String(ref title) => title.to_owned(),
+            _ => return err::InvalidResponse { resp: data }.fail(),
+        };
+        let mut meta = artwork_meta(&data["body"]);
+        // ugoira is extracted as the zip of its frames, with frame timing in meta
+        if data["body"]["illustType"].as_u64() == Some(ugoira::ILLUST_TYPE_UGOIRA) {
+            let ugoira = self.ugoira_meta().await?;
+            meta.insert("ugoira".to_owned(), ugoira.to_json());
+            let origin = Origin::new(vec![Track::Image(ugoira.src)], title.clone()).with_meta(meta);
+            return Ok(Playlist::new(vec![origin], title));
+        }
+        let pages = self.raw_urls().await?;
+        let mut tracks = Vec::with_capacity(pages.len());
+        let mut images = Vec::with_capacity(pages.len());
+        for page in &pages {
+            let (url, image) = page_image(page, self.size)?;
+            tracks.push(Track::Image(url));
+            images.push(image);
+        }
+        meta.insert("images".to_owned(), images.into());
+        let raws = Origin::new(tracks, title.clone()).with_meta(meta);
+        Ok(Playlist::new(vec![raws], title))
+    }
+}
+
+impl AsClient for Pixiv {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
+
+/// A extractor for pixiv user collection (bookmark).
+/// Works only when cookies are passed, which are used for every work as well.
+/// Fails with `Error::LoginRequired` when pixiv refuses the bookmarks for not being logged in
+#[derive(Debug)]
+pub struct Collection {
+    client: Client,
+    api: Url,
+    uid: String,
+    size: Size,
+    private: bool,
+    tag: String,
+    offset: u64,
+    max: Option<u64>,
+    since: Option<String>,
+    until: Option<String>,
+    concurrency: usize,
+}
+
+/// A bookmarked work
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bookmark {
+    pub id: String,
+    pub title: String,
+    /// upload date of the work, e.g. `2021-08-25T00:00:09+09:00`
+    pub create_date: String,
+}
+
+impl Bookmark {
+    /// Parses an item in `body.works` of `BOOKMARKS_API`
+    pub fn from_json(v: &Value) -> Result<Self, Error> {
+        let id = v["id"]
+            .as_str()
+            .map(ToOwned::to_owned)
+            .or_else(|| v["id"].as_u64().map(|id| id.to_string()));
+        match id {
+            Some(id) => Ok(Self {
+                id,
+                title: v["title"].as_str().unwrap_or_default().to_owned(),
+                create_date: v["createDate"].as_str().unwrap_or_default().to_owned(),
+            }),
+            None => err::InvalidResponse { resp: v.clone() }.fail(),
+        }
+    }
+}
+
+impl Collection {
+    /// Accepts `/users/{uid}/bookmarks/artworks` and `bookmark.php?id={uid}`,
+    /// extracting private bookmarks with `rest=hide`
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url = Url::parse(s)?;
+        match Link::parse(&url)? {
+            Link::Bookmarks { uid, private: true } => Ok(Self::with_uid(uid).private()),
+            Link::Bookmarks { uid, .. } => Ok(Self::with_uid(uid)),
+            _ => Err(Error::InvalidUrl { url }),
+        }
+    }
+    pub fn with_uid(uid: String) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), uid)
+    }
+    pub fn with_client(client: Client, uid: String) -> Self {
+        Self {
+            client,
+            api: BOOKMARKS_API.parse().unwrap(),
+            uid,
+            size: Size::default(),
+            private: false,
+            tag: String::new(),
+            offset: 0,
+            max: None,
+            since: None,
+            until: None,
+            concurrency: CONCURRENCY,
+        }
+    }
+    /// Replaces `BOOKMARKS_API`, only meant for tests against stand-in servers
+    #[doc(hidden)]
+    pub fn api(mut self, api: Url) -> Self {
+        self.api = api;
+        self
+    }
+    /// Size of images to extract, the original ones by default
+    pub fn size(mut self, size: Size) -> Self {
+        self.size = size;
+        self
+    }
+    /// Extracts private bookmarks (`rest=hide`), which are visible only to their owner
+    pub fn private(mut self) -> Self {
+        self.private = true;
+        self
+    }
+    /// Only extracts bookmarks tagged with `tag` by the user
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = tag.into();
+        self
+    }
+    /// Skips the `offset` latest bookmarks
+    pub fn offset(mut self, offset: u64) -> Self {
+        self.offset = offset;
+        self
+    }
+    /// Stops after `max` bookmarks, counted before filtering by dates
+    pub fn max(mut self, max: u64) -> Self {
+        self.max = Some(max);
+        self
+    }
+    /// Only extracts works uploaded on or after `date` (`YYYY-MM-DD`)
+    pub fn since(mut self, date: impl Into<String>) -> Self {
+        self.since = Some(date.into());
+        self
+    }
+    /// Only extracts works uploaded on or before `date` (`YYYY-MM-DD`)
+    pub fn until(mut self, date: impl Into<String>) -> Self {
+        self.until = Some(date.into());
+        self
+    }
+    /// Number of works extracted at the same time, 4 by default
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    fn in_dates(&self, bookmark: &Bookmark) -> bool {
+        let date = bookmark.create_date.get(..10).unwrap_or_default();
+        self.since.as_deref().is_none_or(|since| date >= since)
+            && self.until.as_deref().is_none_or(|until| date <= until)
+    }
+    /// Returns `limit` bookmarks from `offset` and the count of bookmarks
+    pub async fn page(&self, offset: u64, limit: u64) -> Result<(Vec<Bookmark>, u64), Error> {
+        let url = self.api.join(&format!("{}/illusts/bookmarks", self.uid))?;
+        let url = Url::parse_with_params(
+            url.as_str(),
+            [
+                ("tag", self.tag.as_str()),
+                ("offset", &offset.to_string()),
+                ("limit", &limit.to_string()),
+                ("rest", if self.private { "hide" } else { "show" }),
+            ],
+        )?;
+        let data: Value = self.client.send_json_request(url).await?;
+        if data["error"].as_bool() == Some(true) {
+            // pixiv refuses bookmarks of nonexistent users as well
+            self.check_login().await?;
+            return err::InvalidResponse { resp: data }.fail();
+        }
+        let bookmarks = match data["body"]["works"] {
+            Value::Array(ref works) => works
+                .iter()
+                .map(Bookmark::from_json)
+                .collect::<Result<_, _>>()?,
+            _ => return err::InvalidResponse { resp: data }.fail(),
+        };
+        Ok((bookmarks, data["body"]["total"].as_u64().unwrap_or(0)))
+    }
+    /// Pixiv returns no bookmarks instead of errors sometimes when not logged in
+    async fn check_login(&self) -> Result<(), Error> {
+        let data: Value = self
+            .client
+            .send_json_request(self.api.join(LOGIN_CHECK_PATH)?)
+            .await?;
+        match data["error"].as_bool() {
+            Some(false) => Ok(()),
+            _ => err::LoginRequired { resp: data }.fail(),
+        }
+    }
+    /// Returns bookmarks in the window without extracting them, latest first
+    pub async fn bookmarks(&self) -> Result<Vec<Bookmark>, Error> {
+        let mut bookmarks = Vec::new();
+        let mut offset = self.offset;
+        let mut fetched = 0;
+        loop {
+            let limit = match self.max {
+                Some(max) => LIMIT.min(max - fetched),
+                None => LIMIT,
+            };
+            if limit == 0 {
+                break;
+            }
+            let (page, total) = self.page(offset, limit).await?;
+            if page.is_empty() && total == 0 && fetched == 0 {
+                self.check_login().await?;
+            }
+            let end = (page.len() as u64) < limit;
+            offset += page.len() as u64;
+            fetched += page.len() as u64;
+            bookmarks.extend(page.into_iter().filter(|b| self.in_dates(b)));
+            if end || offset >= total {
+                break;
+            }
+        }
+        Ok(bookmarks)
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for Collection {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let bookmarks = self.bookmarks().await?;
+        let extractions = bookmarks.into_iter().map(|bookmark| {
+            let mut extractor =
+                Pixiv::with_client(self.client.clone(), bookmark.id).size(self.size);
+            async move { extractor.extract().await }
+        });
+        // ignore invalid illusts, such as deleted ones
+        let origins: Vec<Origin> = stream::iter(extractions)
+            .buffered(self.concurrency)
+            .flat_map(|res| stream::iter(res.unwrap_or_default().into_parts().0))
+            .collect()
+            .await;
+        Ok(Playlist::new(origins, String::new()))
+    }
+}
+
+impl AsClient for Collection {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
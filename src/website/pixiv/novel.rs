@@ -0,0 +1,746 @@
+//! Extractors for novels and novel series.
+//! The body of a novel is converted from pixiv markup into Markdown, and into EPUB with feature `epub`
+use super::{unavailable, Link, Pixiv, CONCURRENCY, HEADERS};
+use crate::{error as err, utils::Client, AsClient, Error, Extract, Meta, Origin, Playlist, Track};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use serde_json::Value;
+use std::collections::HashMap;
+use url::Url;
+
+/// {id}
+pub const NOVEL_API: &str = "https://www.pixiv.net/ajax/novel/";
+/// {id}
+pub const NOVEL_SERIES_API: &str = "https://www.pixiv.net/ajax/novel/series/";
+/// {id}?limit={}&last_order={}&order_by=asc
+pub const NOVEL_SERIES_CONTENT_API: &str = "https://www.pixiv.net/ajax/novel/series_content/";
+const SERIES_LIMIT: u64 = 30;
+
+/// An image embedded in a novel
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ImageRef {
+    /// `[pixivimage:{id}-{page}]`, page starting from 1
+    Illust { id: String, page: usize },
+    /// `[uploadedimage:{id}]`
+    Uploaded(String),
+}
+
+/// A piece of pixiv novel markup
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Text(String),
+    /// `[[rb:{base} > {ruby}]]`
+    Ruby {
+        base: String,
+        ruby: String,
+    },
+    /// `[[jumpuri:{text} > {url}]]`
+    Link {
+        text: String,
+        url: String,
+    },
+    /// `[chapter:{title}]`
+    Chapter(String),
+    /// `[newpage]`
+    NewPage,
+    Image(ImageRef),
+}
+
+/// The body of a novel along with urls of its images
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    pub title: String,
+    pub author: String,
+    /// language code of the novel such as `ja` or `zh-cn`, empty if unknown
+    pub language: String,
+    pub segments: Vec<Segment>,
+    pub images: HashMap<ImageRef, Url>,
+}
+
+/// A extractor for novels (`pixiv.net/novel/show.php?id={id}`).
+/// The body is returned as a Markdown text track, followed by embedded images
+#[derive(Debug)]
+pub struct Novel {
+    client: Client,
+    id: String,
+    concurrency: usize,
+}
+
+/// A extractor for novel series (`pixiv.net/novel/series/{id}`), one origin per chapter in reading order.
+/// Chapters that cannot be extracted, such as deleted ones, are kept without tracks
+/// and marked `unavailable` in the meta
+#[derive(Debug)]
+pub struct NovelSeries {
+    client: Client,
+    id: String,
+    concurrency: usize,
+}
+
+/// Finds the `]` closing the `[` at `start`
+fn closing(s: &str, start: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in s[start..].char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(start + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits `{a} > {b}` of ruby and links
+fn split_pair(inner: &str) -> (String, String) {
+    match inner.split_once('>') {
+        Some((a, b)) => (a.trim().to_owned(), b.trim().to_owned()),
+        None => (inner.trim().to_owned(), String::new()),
+    }
+}
+
+/// Parses a tag without the outermost brackets, e.g. `chapter:title` or `[rb:a > b]`.
+/// Returns `Some(None)` for tags dropped
+fn parse_tag(tag: &str) -> Option<Option<Segment>> {
+    if let Some(inner) = tag.strip_prefix("[rb:").and_then(|t| t.strip_suffix(']')) {
+        let (base, ruby) = split_pair(inner);
+        Some(Some(Segment::Ruby { base, ruby }))
+    } else if let Some(inner) = tag
+        .strip_prefix("[jumpuri:")
+        .and_then(|t| t.strip_suffix(']'))
+    {
+        let (text, url) = split_pair(inner);
+        Some(Some(Segment::Link { text, url }))
+    } else if tag == "newpage" {
+        Some(Some(Segment::NewPage))
+    } else if let Some(title) = tag.strip_prefix("chapter:") {
+        Some(Some(Segment::Chapter(plain_text(&parse(title)))))
+    } else if let Some(image) = tag.strip_prefix("pixivimage:") {
+        let (id, page) = match image.split_once('-') {
+            Some((id, page)) => (id, page.parse().ok()?),
+            None => (image, 1),
+        };
+        Some(Some(Segment::Image(ImageRef::Illust {
+            id: id.trim().to_owned(),
+            page,
+        })))
+    } else if let Some(id) = tag.strip_prefix("uploadedimage:") {
+        Some(Some(Segment::Image(ImageRef::Uploaded(
+            id.trim().to_owned(),
+        ))))
+    } else if tag.starts_with("jump:") {
+        // links to other pages of the novel make no sense outside pixiv
+        Some(None)
+    } else {
+        None
+    }
+}
+
+/// Parses pixiv novel markup. Unknown tags are kept as text
+pub fn parse(content: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut pos = 0;
+    while let Some(offset) = content[pos..].find('[') {
+        let start = pos + offset;
+        let tag = closing(content, start)
+            .and_then(|end| parse_tag(&content[start + 1..end]).map(|tag| (tag, end)));
+        match tag {
+            Some((tag, end)) => {
+                text.push_str(&content[pos..start]);
+                if !text.is_empty() {
+                    segments.push(Segment::Text(std::mem::take(&mut text)));
+                }
+                segments.extend(tag);
+                pos = end + 1;
+            }
+            None => {
+                text.push_str(&content[pos..=start]);
+                pos = start + 1;
+            }
+        }
+    }
+    text.push_str(&content[pos..]);
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    segments
+}
+
+/// Text of `segments` with ruby and links reduced to their text
+fn plain_text(segments: &[Segment]) -> String {
+    segments
+        .iter()
+        .map(|segment| match segment {
+            Segment::Text(text) | Segment::Ruby { base: text, .. } => text.as_str(),
+            Segment::Link { text, .. } => text.as_str(),
+            _ => "",
+        })
+        .collect()
+}
+
+/// Escapes characters which Markdown takes as syntax, so that `text` is rendered as it is.
+/// Block markers such as `#`, `-` and `1.` are escaped only at the start of lines,
+/// which `text` is placed at if `line_start` is set
+fn escape_markdown(text: &str, mut line_start: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    // only digits follow the indentation of the line so far
+    let mut numbered = false;
+    for c in text.chars() {
+        let block = match c {
+            '#' | '-' | '+' | '>' | '=' => line_start,
+            '.' => numbered,
+            _ => false,
+        };
+        if block
+            || matches!(
+                c,
+                '\\' | '`' | '*' | '_' | '[' | ']' | '(' | ')' | '<' | '&' | '~' | '|'
+            )
+        {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        numbered = (line_start || numbered) && c.is_ascii_digit();
+        line_start = c == '\n' || (line_start && (c == ' ' || c == '\u{3000}'));
+    }
+    escaped
+}
+
+/// Escapes the destination of a Markdown link
+fn escape_link_url(url: &str) -> String {
+    let mut escaped = String::with_capacity(url.len());
+    for c in url.chars() {
+        match c {
+            ' ' => escaped.push_str("%20"),
+            '\\' | '(' | ')' | '<' | '>' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Percent encodes `text` into a `data:` url
+pub(crate) fn data_url(mime: &str, text: &str) -> Url {
+    let mut url = format!("data:{};charset=utf-8,", mime);
+    for b in text.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                url.push(b as char)
+            }
+            _ => url.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    // data urls are always valid
+    url.parse().unwrap()
+}
+
+impl Document {
+    /// Parses `body` of `NOVEL_API`. Only images uploaded with the novel are resolved
+    pub fn from_json(body: &Value) -> Result<Self, Error> {
+        let content = match body["content"].as_str() {
+            Some(content) => content,
+            None => return err::InvalidResponse { resp: body.clone() }.fail(),
+        };
+        let images = body["textEmbeddedImages"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(id, image)| {
+                let url = image["urls"]["original"].as_str()?.parse().ok()?;
+                Some((ImageRef::Uploaded(id.clone()), url))
+            })
+            .collect();
+        Ok(Self {
+            title: body["title"].as_str().unwrap_or_default().to_owned(),
+            author: body["userName"].as_str().unwrap_or_default().to_owned(),
+            language: body["language"].as_str().unwrap_or_default().to_owned(),
+            segments: parse(content),
+            images,
+        })
+    }
+    /// Embedded images in order of appearance, without duplicates
+    pub fn image_refs(&self) -> Vec<&ImageRef> {
+        let mut refs = Vec::new();
+        for segment in &self.segments {
+            if let Segment::Image(image) = segment {
+                if !refs.contains(&image) {
+                    refs.push(image);
+                }
+            }
+        }
+        refs
+    }
+    /// Pages split by `[newpage]`
+    pub fn pages(&self) -> impl Iterator<Item = &[Segment]> {
+        self.segments.split(|segment| *segment == Segment::NewPage)
+    }
+    /// Converts the body into Markdown. Line breaks are kept as they are,
+    /// ruby becomes html `<ruby>` and pages are separated by horizontal rules.
+    /// Characters taken as Markdown syntax are escaped
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        for segment in &self.segments {
+            let line_start = md
+                .rsplit('\n')
+                .next()
+                .is_none_or(|line| line.trim().is_empty());
+            match segment {
+                Segment::Text(text) => md.push_str(&escape_markdown(text, line_start)),
+                Segment::Ruby { base, ruby } => md.push_str(&format!(
+                    "<ruby>{}<rt>{}</rt></ruby>",
+                    escape_markdown(base, false),
+                    escape_markdown(ruby, false)
+                )),
+                Segment::Link { text, url } => md.push_str(&format!(
+                    "[{}]({})",
+                    escape_markdown(text, false),
+                    escape_link_url(url)
+                )),
+                Segment::Chapter(title) => {
+                    md.push_str(&format!("\n## {}\n", escape_markdown(title, false)))
+                }
+                Segment::NewPage => md.push_str("\n\n---\n\n"),
+                Segment::Image(image) => {
+                    if let Some(url) = self.images.get(image) {
+                        md.push_str(&format!("![]({})", url));
+                    }
+                }
+            }
+        }
+        md
+    }
+}
+
+#[cfg(feature = "epub")]
+mod epub {
+    use super::{Document, ImageRef, Segment};
+    use crate::{error as err, utils::Client, Error};
+    use std::{collections::HashMap, io::Write};
+    use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+    const CONTAINER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+    fn escape(s: &str) -> String {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+
+    fn media_type(path: &str) -> &'static str {
+        match path
+            .rsplit('.')
+            .next()
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("png") => "image/png",
+            Some("gif") => "image/gif",
+            Some("webp") => "image/webp",
+            _ => "image/jpeg",
+        }
+    }
+
+    fn xhtml(title: &str, body: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head><title>{}</title></head>
+<body>
+{}</body>
+</html>
+"#,
+            escape(title),
+            body
+        )
+    }
+
+    impl Document {
+        /// Downloads embedded images and packs the novel into an EPUB 3 book,
+        /// one xhtml file per page. Images failing to download are left out
+        pub async fn to_epub(&self, client: &Client) -> Result<Vec<u8>, Error> {
+            let mut files = HashMap::new();
+            for (i, image) in self.image_refs().into_iter().enumerate() {
+                let url = match self.images.get(image) {
+                    Some(url) => url,
+                    None => continue,
+                };
+                if let Ok(bytes) = super::super::download(client, url.clone()).await {
+                    let name = url.path().rsplit('/').next().unwrap_or_default();
+                    let ext = match name.rsplit_once('.') {
+                        Some((_, ext)) if !ext.is_empty() => ext,
+                        _ => "jpg",
+                    };
+                    files.insert(image, (format!("images/{}.{}", i, ext), bytes));
+                }
+            }
+            self.pack(&files).map_err(|e| {
+                err::RenderError {
+                    reason: e.to_string(),
+                }
+                .build()
+            })
+        }
+        fn pack(
+            &self,
+            images: &HashMap<&ImageRef, (String, Vec<u8>)>,
+        ) -> Result<Vec<u8>, zip::result::ZipError> {
+            let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+            // `mimetype` must be the first file and stored uncompressed
+            let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+            let deflated = SimpleFileOptions::default();
+            zip.start_file("mimetype", stored)?;
+            zip.write_all(b"application/epub+zip")?;
+            zip.start_file("META-INF/container.xml", deflated)?;
+            zip.write_all(CONTAINER.as_bytes())?;
+
+            let mut manifest = String::new();
+            let mut spine = String::new();
+            let mut toc = String::new();
+            let mut chapters = 0;
+            for (i, page) in self.pages().enumerate() {
+                let name = format!("page{}.xhtml", i + 1);
+                let mut body = String::new();
+                let mut text = String::new();
+                for segment in page {
+                    match segment {
+                        Segment::Text(t) => text.push_str(&escape(t)),
+                        Segment::Ruby { base, ruby } => text.push_str(&format!(
+                            "<ruby>{}<rt>{}</rt></ruby>",
+                            escape(base),
+                            escape(ruby)
+                        )),
+                        Segment::Link { text: t, url } => {
+                            text.push_str(&format!("<a href=\"{}\">{}</a>", escape(url), escape(t)))
+                        }
+                        Segment::Chapter(title) => {
+                            chapters += 1;
+                            let id = format!("c{}", chapters);
+                            text.push_str(&format!("\n<h2 id=\"{}\">{}</h2>\n", id, escape(title)));
+                            toc.push_str(&format!(
+                                "<li><a href=\"{}#{}\">{}</a></li>\n",
+                                name,
+                                id,
+                                escape(title)
+                            ));
+                        }
+                        Segment::Image(image) => {
+                            if let Some((path, _)) = images.get(image) {
+                                text.push_str(&format!("\n<img src=\"{}\" alt=\"\"/>\n", path));
+                            }
+                        }
+                        Segment::NewPage => {}
+                    }
+                }
+                for line in text.trim_matches('\n').split('\n') {
+                    if line.starts_with("<h2") || line.starts_with("<img") {
+                        body.push_str(line);
+                    } else if line.trim().is_empty() {
+                        body.push_str("<p><br/></p>");
+                    } else {
+                        body.push_str(&format!("<p>{}</p>", line));
+                    }
+                    body.push('\n');
+                }
+                zip.start_file(format!("OEBPS/{}", name), deflated)?;
+                zip.write_all(xhtml(&self.title, &body).as_bytes())?;
+                manifest.push_str(&format!(
+                    "<item id=\"p{0}\" href=\"{1}\" media-type=\"application/xhtml+xml\"/>\n",
+                    i + 1,
+                    name
+                ));
+                spine.push_str(&format!("<itemref idref=\"p{}\"/>\n", i + 1));
+            }
+            // novels without chapters are navigated by pages
+            if toc.is_empty() {
+                for i in 1..=self.pages().count() {
+                    toc.push_str(&format!("<li><a href=\"page{0}.xhtml\">{0}</a></li>\n", i));
+                }
+            }
+            let nav = format!("<nav epub:type=\"toc\">\n<ol>\n{}</ol>\n</nav>\n", toc);
+            zip.start_file("OEBPS/nav.xhtml", deflated)?;
+            zip.write_all(xhtml(&self.title, &nav).as_bytes())?;
+            let mut paths = images.values().collect::<Vec<_>>();
+            paths.sort_by(|a, b| a.0.cmp(&b.0));
+            for (i, (path, bytes)) in paths.into_iter().enumerate() {
+                zip.start_file(format!("OEBPS/{}", path), stored)?;
+                zip.write_all(bytes)?;
+                manifest.push_str(&format!(
+                    "<item id=\"i{}\" href=\"{}\" media-type=\"{}\"/>\n",
+                    i + 1,
+                    path,
+                    media_type(path)
+                ));
+            }
+            let opf = format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="id">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+<dc:identifier id="id">{}</dc:identifier>
+<dc:title>{}</dc:title>
+<dc:creator>{}</dc:creator>
+<dc:language>{}</dc:language>
+<meta property="dcterms:modified">2000-01-01T00:00:00Z</meta>
+</metadata>
+<manifest>
+<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+{}</manifest>
+<spine>
+{}</spine>
+</package>
+"#,
+                escape(&format!("pixiv:{}:{}", self.author, self.title)),
+                escape(&self.title),
+                escape(&self.author),
+                // required by EPUB, `und` being the code of undetermined languages
+                escape(if self.language.is_empty() {
+                    "und"
+                } else {
+                    &self.language
+                }),
+                manifest,
+                spine
+            );
+            zip.start_file("OEBPS/content.opf", deflated)?;
+            zip.write_all(opf.as_bytes())?;
+            Ok(zip.finish()?.into_inner())
+        }
+    }
+}
+
+impl Novel {
+    /// Accepts `pixiv.net/novel/show.php?id={id}`
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url = Url::parse(s)?;
+        match Link::parse(&url)? {
+            Link::Novel(id) => Ok(Self::with_id(id)),
+            _ => Err(Error::InvalidUrl { url }),
+        }
+    }
+    pub fn with_id(id: String) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), id)
+    }
+    pub fn with_client(client: Client, id: String) -> Self {
+        Self {
+            client,
+            id,
+            concurrency: CONCURRENCY,
+        }
+    }
+    /// Number of illustrations resolved at the same time, 4 by default
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    pub async fn novel_json(&self) -> Result<Value, Error> {
+        let url = Url::parse(NOVEL_API)?.join(&self.id)?;
+        let data: Value = self.client.send_json_request(url).await?;
+        match data["body"]["content"] {
+            Value::String(_) => Ok(data),
+            _ => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+    /// Returns the body with images from illustrations resolved.
+    /// Illustrations unavailable are left out
+    pub async fn document(&self) -> Result<Document, Error> {
+        let data = self.novel_json().await?;
+        let mut document = Document::from_json(&data["body"])?;
+        let illusts = document
+            .image_refs()
+            .into_iter()
+            .filter_map(|image| match image {
+                ImageRef::Illust { id, page } => Some((id.clone(), *page)),
+                ImageRef::Uploaded(_) => None,
+            })
+            .collect::<Vec<_>>();
+        // pages of an illustration are fetched once, however many of them are embedded
+        let mut ids = illusts.iter().map(|(id, _)| id.clone()).collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids.dedup();
+        let extractions = ids.into_iter().map(|id| {
+            let pixiv = Pixiv::with_client(self.client.clone(), id.clone());
+            async move { (id, pixiv.raw_urls().await.ok()) }
+        });
+        let pages = stream::iter(extractions)
+            .buffered(self.concurrency)
+            .collect::<HashMap<_, _>>()
+            .await;
+        for (id, page) in illusts {
+            let url = pages
+                .get(&id)
+                .and_then(Option::as_ref)
+                .and_then(|pages| pages.get(page.saturating_sub(1)))
+                .and_then(|page| page["urls"]["original"].as_str()?.parse().ok());
+            if let Some(url) = url {
+                document.images.insert(ImageRef::Illust { id, page }, url);
+            }
+        }
+        Ok(document)
+    }
+    async fn origin(&self) -> Result<Origin, Error> {
+        let document = self.document().await?;
+        let mut tracks = vec![Track::Text(data_url(
+            "text/markdown",
+            &document.to_markdown(),
+        ))];
+        tracks.extend(
+            document
+                .image_refs()
+                .into_iter()
+                .filter_map(|image| document.images.get(image).cloned())
+                .map(Track::Image),
+        );
+        let mut meta = Meta::new();
+        meta.insert("nid".to_owned(), self.id.clone().into());
+        meta.insert("author".to_owned(), document.author.into());
+        Ok(Origin::new(tracks, document.title).with_meta(meta))
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for Novel {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let origin = self.origin().await?;
+        let title = origin.title.clone();
+        Ok(Playlist::new(vec![origin], title))
+    }
+}
+
+impl AsClient for Novel {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
+
+/// Parses `body` of `NOVEL_SERIES_CONTENT_API` into ids and chapter numbers
+pub fn series_contents(body: &Value) -> Result<Vec<(String, u64)>, Error> {
+    let contents = match (&body["page"]["seriesContents"], &body["seriesContents"]) {
+        (Value::Array(contents), _) | (_, Value::Array(contents)) => contents,
+        _ => return err::InvalidResponse { resp: body.clone() }.fail(),
+    };
+    contents
+        .iter()
+        .map(|content| {
+            match (
+                content["id"].as_str(),
+                content["series"]["contentOrder"].as_u64(),
+            ) {
+                (Some(id), Some(order)) => Ok((id.to_owned(), order)),
+                _ => err::InvalidResponse {
+                    resp: content.clone(),
+                }
+                .fail(),
+            }
+        })
+        .collect()
+}
+
+impl NovelSeries {
+    /// Accepts `pixiv.net/novel/series/{id}`
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url = Url::parse(s)?;
+        match Link::parse(&url)? {
+            Link::NovelSeries(id) => Ok(Self::with_id(id)),
+            _ => Err(Error::InvalidUrl { url }),
+        }
+    }
+    pub fn with_id(id: String) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), id)
+    }
+    pub fn with_client(client: Client, id: String) -> Self {
+        Self {
+            client,
+            id,
+            concurrency: CONCURRENCY,
+        }
+    }
+    /// Number of chapters extracted at the same time, 4 by default
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    /// Returns the title and ids of all chapters with their chapter numbers, in reading order
+    pub async fn chapters(&self) -> Result<(String, Vec<(String, u64)>), Error> {
+        let url = Url::parse(NOVEL_SERIES_API)?.join(&self.id)?;
+        let data: Value = self.client.send_json_request(url).await?;
+        let title = match data["body"]["title"] {
+            Value::String(ref title) => title.clone(),
+            _ => return err::InvalidResponse { resp: data }.fail(),
+        };
+        let base = Url::parse(NOVEL_SERIES_CONTENT_API)?.join(&self.id)?;
+        let mut chapters = Vec::new();
+        loop {
+            let url = Url::parse_with_params(
+                base.as_str(),
+                [
+                    ("limit", SERIES_LIMIT.to_string()),
+                    ("last_order", chapters.len().to_string()),
+                    ("order_by", "asc".to_owned()),
+                ],
+            )?;
+            let data: Value = self.client.send_json_request(url).await?;
+            let page = series_contents(&data["body"])
+                .map_err(|_| err::InvalidResponse { resp: data.clone() }.build())?;
+            let end = (page.len() as u64) < SERIES_LIMIT;
+            chapters.extend(page);
+            if end {
+                break;
+            }
+        }
+        chapters.sort_by_key(|(_, order)| *order);
+        Ok((title, chapters))
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for NovelSeries {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let (title, chapters) = self.chapters().await?;
+        let extractions = chapters.into_iter().map(|(id, order)| {
+            let novel = Novel::with_client(self.client.clone(), id.clone());
+            async move {
+                let mut origin = match novel.origin().await {
+                    Ok(origin) => origin,
+                    Err(Error::InvalidResponse { .. }) => {
+                        let mut origin = unavailable(String::new());
+                        origin.meta.insert("nid".to_owned(), id.into());
+                        origin
+                    }
+                    Err(e) => return Err(e),
+                };
+                origin.meta.insert("order".to_owned(), order.into());
+                Ok::<_, Error>(origin)
+            }
+        });
+        let origins = stream::iter(extractions)
+            .buffered(self.concurrency)
+            .try_collect()
+            .await?;
+        Ok(Playlist::new(origins, title))
+    }
+}
+
+impl AsClient for NovelSeries {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
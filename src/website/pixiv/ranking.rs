@@ -0,0 +1,307 @@
+//! Extractor for rankings (`pixiv.net/ranking.php`)
+use super::{is_unavailable, unavailable, Link, Pixiv, Size, CONCURRENCY, HEADERS};
+use crate::{error as err, utils::Client, AsClient, Error, Extract, Playlist};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use serde_json::Value;
+use url::Url;
+
+/// ?mode={}&content={}&date={YYYYMMDD}&p={}&format=json, 50 works per page
+pub const RANKING_API: &str = "https://www.pixiv.net/ranking.php";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    #[default]
+    Daily,
+    Weekly,
+    Monthly,
+    Rookie,
+    Original,
+    /// popular among male users
+    Male,
+    /// popular among female users
+    Female,
+    DailyAi,
+    DailyR18,
+    WeeklyR18,
+    MaleR18,
+    FemaleR18,
+    DailyR18Ai,
+    R18G,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Content {
+    /// all kinds of works, only available for some modes such as daily and weekly
+    #[default]
+    All,
+    Illust,
+    Manga,
+    Ugoira,
+}
+
+/// A ranked work
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub rank: u64,
+    pub pid: String,
+    pub title: String,
+}
+
+/// A extractor for rankings, each origin carrying its `rank` in the meta.
+/// Works that cannot be extracted, such as deleted ones, are kept without tracks
+/// and marked `unavailable` in the meta along with their `pid`.
+/// R-18 modes require cookies of an account allowing R-18 works
+#[derive(Debug)]
+pub struct Ranking {
+    client: Client,
+    mode: Mode,
+    content: Content,
+    date: Option<String>,
+    max: Option<u64>,
+    size: Size,
+    concurrency: usize,
+}
+
+impl Mode {
+    const ALL: [Self; 14] = [
+        Self::Daily,
+        Self::Weekly,
+        Self::Monthly,
+        Self::Rookie,
+        Self::Original,
+        Self::Male,
+        Self::Female,
+        Self::DailyAi,
+        Self::DailyR18,
+        Self::WeeklyR18,
+        Self::MaleR18,
+        Self::FemaleR18,
+        Self::DailyR18Ai,
+        Self::R18G,
+    ];
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+            Self::Monthly => "monthly",
+            Self::Rookie => "rookie",
+            Self::Original => "original",
+            Self::Male => "male",
+            Self::Female => "female",
+            Self::DailyAi => "daily_ai",
+            Self::DailyR18 => "daily_r18",
+            Self::WeeklyR18 => "weekly_r18",
+            Self::MaleR18 => "male_r18",
+            Self::FemaleR18 => "female_r18",
+            Self::DailyR18Ai => "daily_r18_ai",
+            Self::R18G => "r18g",
+        }
+    }
+    /// Parses `mode` in urls of rankings
+    pub fn parse(mode: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.as_str() == mode)
+    }
+    pub fn is_r18(&self) -> bool {
+        matches!(
+            self,
+            Self::DailyR18
+                | Self::WeeklyR18
+                | Self::MaleR18
+                | Self::FemaleR18
+                | Self::DailyR18Ai
+                | Self::R18G
+        )
+    }
+}
+
+impl Content {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::All => "all",
+            Self::Illust => "illust",
+            Self::Manga => "manga",
+            Self::Ugoira => "ugoira",
+        }
+    }
+    /// Parses `content` in urls of rankings
+    pub fn parse(content: &str) -> Option<Self> {
+        [Self::All, Self::Illust, Self::Manga, Self::Ugoira]
+            .into_iter()
+            .find(|c| c.as_str() == content)
+    }
+}
+
+/// Parses a page of `RANKING_API`, returning ranked works and the next page if any
+pub fn ranking_page(data: &Value) -> Result<(Vec<Entry>, Option<u64>), Error> {
+    let contents = match data["contents"] {
+        Value::Array(ref contents) => contents,
+        _ => return err::InvalidResponse { resp: data.clone() }.fail(),
+    };
+    let entries = contents
+        .iter()
+        .map(
+            |content| match (content["rank"].as_u64(), content["illust_id"].as_u64()) {
+                (Some(rank), Some(pid)) => Ok(Entry {
+                    rank,
+                    pid: pid.to_string(),
+                    title: content["title"].as_str().unwrap_or_default().to_owned(),
+                }),
+                _ => err::InvalidResponse {
+                    resp: content.clone(),
+                }
+                .fail(),
+            },
+        )
+        .collect::<Result<_, Error>>()?;
+    // `next` is `false` on the last page
+    Ok((entries, data["next"].as_u64()))
+}
+
+impl Ranking {
+    /// Accepts `pixiv.net/ranking.php?mode={}&content={}&date={}`, all parameters being optional
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url = Url::parse(s)?;
+        if Link::parse(&url)? != Link::Ranking {
+            return Err(Error::InvalidUrl { url });
+        }
+        let mut ranking = Self::with_mode(Mode::default());
+        for (key, value) in url.query_pairs() {
+            match &*key {
+                "mode" => match Mode::parse(&value) {
+                    Some(mode) => ranking.mode = mode,
+                    None => return Err(Error::InvalidUrl { url }),
+                },
+                "content" => match Content::parse(&value) {
+                    Some(content) => ranking.content = content,
+                    None => return Err(Error::InvalidUrl { url }),
+                },
+                "date" => ranking = ranking.date(value),
+                _ => {}
+            }
+        }
+        Ok(ranking)
+    }
+    pub fn with_mode(mode: Mode) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), mode)
+    }
+    pub fn with_client(client: Client, mode: Mode) -> Self {
+        Self {
+            client,
+            mode,
+            content: Content::default(),
+            date: None,
+            max: None,
+            size: Size::default(),
+            concurrency: CONCURRENCY,
+        }
+    }
+    pub fn content(mut self, content: Content) -> Self {
+        self.content = content;
+        self
+    }
+    /// Ranking of `date` (`YYYYMMDD`), the latest one by default
+    pub fn date(mut self, date: impl Into<String>) -> Self {
+        self.date = Some(date.into());
+        self
+    }
+    /// Only extracts the top `max` works
+    pub fn max(mut self, max: u64) -> Self {
+        self.max = Some(max);
+        self
+    }
+    /// Size of images to extract, the original ones by default
+    pub fn size(mut self, size: Size) -> Self {
+        self.size = size;
+        self
+    }
+    /// Number of works extracted at the same time, 4 by default
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    /// Returns page `p` (starting from 1) of the ranking
+    pub async fn ranking_json(&self, p: u64) -> Result<Value, Error> {
+        let mut url = Url::parse_with_params(
+            RANKING_API,
+            [
+                ("mode", self.mode.as_str()),
+                ("p", &p.to_string()),
+                ("format", "json"),
+            ],
+        )?;
+        if self.content != Content::All {
+            url.query_pairs_mut()
+                .append_pair("content", self.content.as_str());
+        }
+        if let Some(ref date) = self.date {
+            url.query_pairs_mut().append_pair("date", date);
+        }
+        let data: Value = self.client.send_json_request(url).await?;
+        match data["error"] {
+            // e.g. R-18 rankings without logging in
+            Value::String(_) if self.mode.is_r18() => err::LoginRequired { resp: data }.fail(),
+            Value::String(_) => err::InvalidResponse { resp: data }.fail(),
+            _ => Ok(data),
+        }
+    }
+    /// Returns ranked works without extracting them, and the date of the ranking
+    pub async fn entries(&self) -> Result<(Vec<Entry>, String), Error> {
+        let mut entries = Vec::new();
+        let mut date = String::new();
+        let mut p = Some(1);
+        while let Some(page) = p {
+            if self.max.is_some_and(|max| entries.len() as u64 >= max) {
+                break;
+            }
+            let data = self.ranking_json(page).await?;
+            let (found, next) = ranking_page(&data)?;
+            date = data["date"].as_str().unwrap_or_default().to_owned();
+            entries.extend(found);
+            p = next;
+        }
+        if let Some(max) = self.max {
+            entries.truncate(max as usize);
+        }
+        Ok((entries, date))
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for Ranking {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let (entries, date) = self.entries().await?;
+        let extractions = entries.into_iter().map(|entry| {
+            let mut extractor =
+                Pixiv::with_client(self.client.clone(), entry.pid.clone()).size(self.size);
+            async move {
+                let mut origin = match extractor.extract().await {
+                    // a work is always extracted into one origin
+                    Ok(playlist) => playlist.into_parts().0.swap_remove(0),
+                    Err(e) if is_unavailable(&e) => {
+                        let mut origin = unavailable(entry.title);
+                        origin.meta.insert("pid".to_owned(), entry.pid.into());
+                        origin
+                    }
+                    Err(e) => return Err(e),
+                };
+                origin.meta.insert("rank".to_owned(), entry.rank.into());
+                Ok::<_, Error>(origin)
+            }
+        });
+        let origins = stream::iter(extractions)
+            .buffered(self.concurrency)
+            .try_collect()
+            .await?;
+        let title = format!("{} {} {}", self.mode.as_str(), self.content.as_str(), date);
+        Ok(Playlist::new(origins, title))
+    }
+}
+
+impl AsClient for Ranking {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
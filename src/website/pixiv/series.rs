@@ -0,0 +1,170 @@
+//! Extractor for manga series (`pixiv.net/user/{uid}/series/{id}`)
+use super::{unavailable, Link, Pixiv, Size, CONCURRENCY, HEADERS};
+use crate::{error as err, utils::Client, AsClient, Error, Extract, Playlist};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use serde_json::Value;
+use url::Url;
+
+/// {id}?p={page}, 12 chapters per page
+pub const SERIES_API: &str = "https://www.pixiv.net/ajax/series/";
+
+/// A chapter of a series
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    /// chapter number, starting from 1
+    pub order: u64,
+    pub id: String,
+    pub title: String,
+}
+
+/// A extractor for manga series, one origin per chapter in reading order.
+/// Chapter numbers are put into the meta as `order`.
+/// Chapters that cannot be extracted, such as deleted ones, are kept without tracks
+/// and marked `unavailable` in the meta
+#[derive(Debug)]
+pub struct Series {
+    client: Client,
+    id: String,
+    concurrency: usize,
+    size: Size,
+}
+
+/// Parses `body` of `SERIES_API`, returning chapters on the page and the count of chapters
+pub fn series_page(body: &Value) -> Result<(Vec<Chapter>, u64), Error> {
+    let entries = match body["page"]["series"] {
+        Value::Array(ref entries) => entries,
+        _ => return err::InvalidResponse { resp: body.clone() }.fail(),
+    };
+    let thumbnails = body["thumbnails"]["illust"].as_array();
+    let chapters = entries
+        .iter()
+        .map(|entry| {
+            let id = entry["workId"]
+                .as_str()
+                .map(ToOwned::to_owned)
+                .or_else(|| entry["workId"].as_u64().map(|id| id.to_string()));
+            match (id, entry["order"].as_u64()) {
+                (Some(id), Some(order)) => {
+                    let title = thumbnails
+                        .into_iter()
+                        .flatten()
+                        .find(|work| work["id"].as_str() == Some(&id))
+                        .and_then(|work| work["title"].as_str())
+                        .unwrap_or_default()
+                        .to_owned();
+                    Ok(Chapter { order, id, title })
+                }
+                _ => err::InvalidResponse {
+                    resp: entry.clone(),
+                }
+                .fail(),
+            }
+        })
+        .collect::<Result<_, Error>>()?;
+    let total = body["page"]["total"]
+        .as_u64()
+        .or_else(|| body["illustSeries"][0]["total"].as_u64())
+        .unwrap_or(0);
+    Ok((chapters, total))
+}
+
+impl Series {
+    /// Accepts `pixiv.net/user/{uid}/series/{id}`
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url = Url::parse(s)?;
+        match Link::parse(&url)? {
+            Link::Series(id) => Ok(Self::with_id(id)),
+            _ => Err(Error::InvalidUrl { url }),
+        }
+    }
+    pub fn with_id(id: String) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), id)
+    }
+    pub fn with_client(client: Client, id: String) -> Self {
+        Self {
+            client,
+            id,
+            concurrency: CONCURRENCY,
+            size: Size::default(),
+        }
+    }
+    /// Size of images to extract, the original ones by default
+    pub fn size(mut self, size: Size) -> Self {
+        self.size = size;
+        self
+    }
+    /// Number of chapters extracted at the same time, 4 by default
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    /// Returns data of page `p` (starting from 1)
+    pub async fn series_json(&self, p: u64) -> Result<Value, Error> {
+        let url = Url::parse(SERIES_API)?.join(&self.id)?;
+        let url = Url::parse_with_params(url.as_str(), [("p", p.to_string())])?;
+        self.client.send_json_request(url).await
+    }
+    /// Returns the title and all chapters of the series in reading order
+    pub async fn chapters(&self) -> Result<(String, Vec<Chapter>), Error> {
+        let mut title = String::new();
+        let mut chapters = Vec::new();
+        for p in 1.. {
+            let data = self.series_json(p).await?;
+            let (page, total) = series_page(&data["body"])
+                .map_err(|_| err::InvalidResponse { resp: data.clone() }.build())?;
+            if p == 1 {
+                title = data["body"]["illustSeries"][0]["title"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_owned();
+            }
+            let end = page.is_empty();
+            chapters.extend(page);
+            if end || chapters.len() as u64 >= total {
+                break;
+            }
+        }
+        chapters.sort_by_key(|chapter| chapter.order);
+        chapters.dedup_by_key(|chapter| chapter.order);
+        Ok((title, chapters))
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for Series {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let (title, chapters) = self.chapters().await?;
+        let extractions = chapters.into_iter().map(|chapter| {
+            let mut extractor =
+                Pixiv::with_client(self.client.clone(), chapter.id.clone()).size(self.size);
+            async move {
+                let mut origin = match extractor.extract().await {
+                    // a work is always extracted into one origin
+                    Ok(playlist) => playlist.into_parts().0.swap_remove(0),
+                    Err(Error::InvalidResponse { .. }) => unavailable(String::new()),
+                    Err(e) => return Err(e),
+                };
+                if !chapter.title.is_empty() {
+                    origin.title = chapter.title;
+                }
+                origin.meta.insert("pid".to_owned(), chapter.id.into());
+                origin.meta.insert("order".to_owned(), chapter.order.into());
+                Ok::<_, Error>(origin)
+            }
+        });
+        let origins = stream::iter(extractions)
+            .buffered(self.concurrency)
+            .try_collect()
+            .await?;
+        Ok(Playlist::new(origins, title))
+    }
+}
+
+impl AsClient for Series {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
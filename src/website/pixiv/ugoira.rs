@@ -0,0 +1,217 @@
+//! Ugoira (うごイラ), animated illustrations shipped as a zip of frames.
+//! Rendering frames into animated images requires feature `ugoira`
+use crate::{error as err, utils::Client, Error};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use url::Url;
+
+/// `illustType` of ugoira works
+pub const ILLUST_TYPE_UGOIRA: u64 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Frame {
+    /// file name in the zip
+    pub file: String,
+    /// milliseconds to show the frame
+    pub delay: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UgoiraMeta {
+    /// zip of original frames
+    pub src: Url,
+    pub mime_type: String,
+    pub frames: Vec<Frame>,
+}
+
+impl UgoiraMeta {
+    /// Parses `body` of `ajax/illust/{pid}/ugoira_meta`
+    pub fn from_json(body: &Value) -> Result<Self, Error> {
+        let src = body["originalSrc"]
+            .as_str()
+            .or_else(|| body["src"].as_str());
+        let frames = Vec::<Frame>::deserialize(&body["frames"]);
+        match (src, frames) {
+            (Some(src), Ok(frames)) => Ok(Self {
+                src: src.parse()?,
+                mime_type: body["mime_type"].as_str().unwrap_or_default().to_owned(),
+                frames,
+            }),
+            _ => err::InvalidResponse { resp: body.clone() }.fail(),
+        }
+    }
+    /// Frame timing put into the meta of extracted origins
+    pub fn to_json(&self) -> Value {
+        json!({
+            "mime_type": self.mime_type,
+            "frames": self.frames,
+        })
+    }
+    /// Downloads the zip of frames
+    pub async fn download(&self, client: &Client) -> Result<Vec<u8>, Error> {
+        super::download(client, self.src.clone()).await
+    }
+}
+
+#[cfg(feature = "ugoira")]
+pub use render::{render, Format};
+
+#[cfg(feature = "ugoira")]
+mod render {
+    use super::Frame;
+    use crate::{error as err, Error};
+    use image::{codecs, ExtendedColorType, ImageEncoder, RgbaImage};
+    use std::io::{Cursor, Read};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Format {
+        Gif,
+        Apng,
+        /// lossless animated webp
+        WebP,
+    }
+
+    fn render_error(reason: impl ToString) -> Error {
+        err::RenderError {
+            reason: reason.to_string(),
+        }
+        .build()
+    }
+
+    /// Decodes frames in `zip` in the order of `frames`
+    fn decode(zip: &[u8], frames: &[Frame]) -> Result<Vec<RgbaImage>, Error> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(zip)).map_err(render_error)?;
+        let images = frames
+            .iter()
+            .map(|frame| {
+                let mut buf = Vec::new();
+                archive
+                    .by_name(&frame.file)
+                    .map_err(render_error)?
+                    .read_to_end(&mut buf)?;
+                Ok(image::load_from_memory(&buf)
+                    .map_err(render_error)?
+                    .to_rgba8())
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        match images.first() {
+            Some(first) if images.iter().all(|i| i.dimensions() == first.dimensions()) => {
+                Ok(images)
+            }
+            Some(_) => Err(render_error("frames differ in size")),
+            None => Err(render_error("no frames")),
+        }
+    }
+
+    /// Renders frames in `zip` into an animated image looping forever
+    pub fn render(zip: &[u8], frames: &[Frame], format: Format) -> Result<Vec<u8>, Error> {
+        let images = decode(zip, frames)?;
+        let delays = frames.iter().map(|f| f.delay);
+        match format {
+            Format::Gif => gif(images, delays),
+            Format::Apng => apng(images, delays),
+            Format::WebP => webp(images, delays),
+        }
+    }
+
+    fn gif(images: Vec<RgbaImage>, delays: impl Iterator<Item = u32>) -> Result<Vec<u8>, Error> {
+        let mut buf = Vec::new();
+        {
+            let mut encoder = codecs::gif::GifEncoder::new_with_speed(&mut buf, 10);
+            encoder
+                .set_repeat(codecs::gif::Repeat::Infinite)
+                .map_err(render_error)?;
+            let frames = images.into_iter().zip(delays).map(|(image, delay)| {
+                let delay = image::Delay::from_numer_denom_ms(delay, 1);
+                image::Frame::from_parts(image, 0, 0, delay)
+            });
+            encoder.encode_frames(frames).map_err(render_error)?;
+        }
+        Ok(buf)
+    }
+
+    fn apng(images: Vec<RgbaImage>, delays: impl Iterator<Item = u32>) -> Result<Vec<u8>, Error> {
+        let (width, height) = images[0].dimensions();
+        let mut buf = Vec::new();
+        let mut encoder = png::Encoder::new(&mut buf, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .set_animated(images.len() as u32, 0)
+            .map_err(render_error)?;
+        let mut writer = encoder.write_header().map_err(render_error)?;
+        for (image, delay) in images.iter().zip(delays) {
+            let delay = delay.min(u16::MAX as u32) as u16;
+            writer.set_frame_delay(delay, 1000).map_err(render_error)?;
+            writer
+                .write_image_data(image.as_raw())
+                .map_err(render_error)?;
+        }
+        writer.finish().map_err(render_error)?;
+        Ok(buf)
+    }
+
+    fn webp(images: Vec<RgbaImage>, delays: impl Iterator<Item = u32>) -> Result<Vec<u8>, Error> {
+        let (width, height) = images[0].dimensions();
+        let mut chunks = Vec::new();
+        // VP8X with animation and alpha flags
+        let mut vp8x = vec![0x12, 0, 0, 0];
+        vp8x.extend_from_slice(&u24(width - 1));
+        vp8x.extend_from_slice(&u24(height - 1));
+        push_chunk(&mut chunks, b"VP8X", &vp8x);
+        // transparent background, looping forever
+        push_chunk(&mut chunks, b"ANIM", &[0, 0, 0, 0, 0, 0]);
+        for (image, delay) in images.iter().zip(delays) {
+            let mut still = Vec::new();
+            codecs::webp::WebPEncoder::new_lossless(&mut still)
+                .write_image(image.as_raw(), width, height, ExtendedColorType::Rgba8)
+                .map_err(render_error)?;
+            let bitstream = find_chunk(&still, b"VP8L")
+                .ok_or_else(|| render_error("no VP8L chunk in encoded frame"))?;
+            let mut anmf = Vec::with_capacity(16 + 8 + bitstream.len());
+            // offsets are zero
+            anmf.extend_from_slice(&[0; 6]);
+            anmf.extend_from_slice(&u24(width - 1));
+            anmf.extend_from_slice(&u24(height - 1));
+            anmf.extend_from_slice(&u24(delay.min(0xff_ffff)));
+            // no blending, no disposal
+            anmf.push(0b10);
+            push_chunk(&mut anmf, b"VP8L", bitstream);
+            push_chunk(&mut chunks, b"ANMF", &anmf);
+        }
+        let mut buf = Vec::with_capacity(12 + chunks.len());
+        buf.extend_from_slice(b"RIFF");
+        buf.extend_from_slice(&(4 + chunks.len() as u32).to_le_bytes());
+        buf.extend_from_slice(b"WEBP");
+        buf.extend(chunks);
+        Ok(buf)
+    }
+
+    fn u24(n: u32) -> [u8; 3] {
+        let [a, b, c, _] = n.to_le_bytes();
+        [a, b, c]
+    }
+
+    fn push_chunk(buf: &mut Vec<u8>, fourcc: &[u8; 4], data: &[u8]) {
+        buf.extend_from_slice(fourcc);
+        buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            buf.push(0);
+        }
+    }
+
+    /// Finds payload of the first chunk named `fourcc` in a RIFF WEBP file
+    fn find_chunk<'a>(webp: &'a [u8], fourcc: &[u8; 4]) -> Option<&'a [u8]> {
+        let mut pos = 12;
+        while pos + 8 <= webp.len() {
+            let len = u32::from_le_bytes(webp[pos + 4..pos + 8].try_into().ok()?) as usize;
+            let data = webp.get(pos + 8..pos + 8 + len)?;
+            if &webp[pos..pos + 4] == fourcc {
+                return Some(data);
+            }
+            pos += 8 + len + len % 2;
+        }
+        None
+    }
+}
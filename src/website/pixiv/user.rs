@@ -0,0 +1,237 @@
+//! Extractor for works of a user (`pixiv.net/users/{uid}`)
+use super::{is_unavailable, unavailable, Link, Pixiv, Size, CONCURRENCY, HEADERS};
+use crate::{error as err, utils::Client, AsClient, Error, Extract, Playlist};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use serde_json::Value;
+use url::Url;
+
+/// {uid}/profile/all, ids of all works of a user, and {uid} for the user
+pub const PROFILE_API: &str = "https://www.pixiv.net/ajax/user/";
+/// {uid}/profile/illusts?ids[]={}&work_category=illustManga&is_first_page=0
+const PROFILE_ILLUSTS_PATH: &str = "profile/illusts";
+/// ids requested at once from `PROFILE_ILLUSTS_PATH`
+const BATCH_SIZE: usize = 48;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WorkType {
+    Illust,
+    Manga,
+    Ugoira,
+}
+
+/// A work listed in a user profile, which can be extracted later
+#[derive(Debug, Clone, PartialEq)]
+pub struct Work {
+    pub id: String,
+    pub title: String,
+    pub kind: WorkType,
+    pub tags: Vec<String>,
+    pub user_name: String,
+}
+
+/// A extractor for works of a user (`pixiv.net/users/{uid}`,
+/// `/users/{uid}/illustrations` or `/users/{uid}/manga`), newest first, titled with the name of the user.
+/// Cookies passed to the client are used for every work.
+/// Works that cannot be extracted, such as deleted ones, are kept without tracks
+/// and marked `unavailable` in the meta along with their `pid`
+#[derive(Debug)]
+pub struct User {
+    client: Client,
+    uid: String,
+    kinds: Vec<WorkType>,
+    tag: Option<String>,
+    concurrency: usize,
+    size: Size,
+}
+
+impl WorkType {
+    /// Converts `illustType` of works
+    pub fn from_illust_type(t: u64) -> Option<Self> {
+        match t {
+            0 => Some(Self::Illust),
+            1 => Some(Self::Manga),
+            2 => Some(Self::Ugoira),
+            _ => None,
+        }
+    }
+}
+
+impl Work {
+    /// Parses a work in `body.works` of the profile illusts api
+    pub fn from_json(v: &Value) -> Result<Self, Error> {
+        let id = v["id"]
+            .as_str()
+            .map(ToOwned::to_owned)
+            .or_else(|| v["id"].as_u64().map(|id| id.to_string()));
+        let kind = v["illustType"]
+            .as_u64()
+            .and_then(WorkType::from_illust_type);
+        match (id, kind) {
+            (Some(id), Some(kind)) => Ok(Self {
+                id,
+                title: v["title"].as_str().unwrap_or_default().to_owned(),
+                kind,
+                tags: v["tags"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|tag| tag.as_str().map(ToOwned::to_owned))
+                    .collect(),
+                user_name: v["userName"].as_str().unwrap_or_default().to_owned(),
+            }),
+            _ => err::InvalidResponse { resp: v.clone() }.fail(),
+        }
+    }
+    pub fn extractor(&self, client: Client) -> Pixiv {
+        Pixiv::with_client(client, self.id.clone())
+    }
+}
+
+/// Collects ids of illusts and manga in `body` of `PROFILE_API`, newest first
+pub fn profile_ids(body: &Value) -> Result<Vec<String>, Error> {
+    let mut ids = Vec::new();
+    for key in ["illusts", "manga"] {
+        match body[key] {
+            Value::Object(ref works) => ids.extend(works.keys().cloned()),
+            // empty categories are returned as `[]`
+            Value::Array(_) => {}
+            _ => return err::InvalidResponse { resp: body.clone() }.fail(),
+        }
+    }
+    ids.sort_by_key(|id| std::cmp::Reverse(id.parse::<u64>().unwrap_or_default()));
+    ids.dedup();
+    Ok(ids)
+}
+
+impl User {
+    /// Accepts `/users/{uid}` and its sections, `member.php?id={uid}` and `member_illust.php?id={uid}`
+    pub fn new(s: &str) -> Result<Self, Error> {
+        let url = Url::parse(s)?;
+        match Link::parse(&url)? {
+            Link::User { uid, kinds } => Ok(kinds
+                .into_iter()
+                .fold(Self::with_uid(uid), |user, kind| user.kind(kind))),
+            _ => Err(Error::InvalidUrl { url }),
+        }
+    }
+    pub fn with_uid(uid: String) -> Self {
+        Self::with_client(Client::with_header(HEADERS.clone()), uid)
+    }
+    pub fn with_client(client: Client, uid: String) -> Self {
+        Self {
+            client,
+            uid,
+            kinds: Vec::new(),
+            tag: None,
+            concurrency: CONCURRENCY,
+            size: Size::default(),
+        }
+    }
+    /// Only extracts works of `kind`, can be called several times. All works are extracted by default
+    pub fn kind(mut self, kind: WorkType) -> Self {
+        if !self.kinds.contains(&kind) {
+            self.kinds.push(kind);
+        }
+        self
+    }
+    /// Only extracts works tagged with `tag`
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+    /// Size of images to extract, the original ones by default
+    pub fn size(mut self, size: Size) -> Self {
+        self.size = size;
+        self
+    }
+    /// Number of works extracted at the same time, 4 by default
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    fn matches(&self, work: &Work) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&work.kind))
+            && self.tag.as_ref().is_none_or(|tag| work.tags.contains(tag))
+    }
+    /// Returns the name of the user
+    pub async fn name(&self) -> Result<String, Error> {
+        let url = Url::parse(PROFILE_API)?.join(&self.uid)?;
+        let data: Value = self.client.send_json_request(url).await?;
+        match data["body"]["name"] {
+            Value::String(ref name) => Ok(name.to_owned()),
+            _ => err::InvalidResponse { resp: data }.fail(),
+        }
+    }
+    /// Returns ids of all works of the user, newest first
+    pub async fn ids(&self) -> Result<Vec<String>, Error> {
+        let url = Url::parse(PROFILE_API)?.join(&format!("{}/profile/all", self.uid))?;
+        let data: Value = self.client.send_json_request(url).await?;
+        profile_ids(&data["body"]).map_err(|_| err::InvalidResponse { resp: data }.build())
+    }
+    /// Returns works of the user matching the filters without extracting them
+    pub async fn works(&self) -> Result<Vec<Work>, Error> {
+        let ids = self.ids().await?;
+        let base =
+            Url::parse(PROFILE_API)?.join(&format!("{}/{}", self.uid, PROFILE_ILLUSTS_PATH))?;
+        let mut works = Vec::with_capacity(ids.len());
+        for batch in ids.chunks(BATCH_SIZE) {
+            let mut url = base.clone();
+            url.query_pairs_mut()
+                .extend_pairs(batch.iter().map(|id| ("ids[]", id)))
+                .append_pair("work_category", "illustManga")
+                .append_pair("is_first_page", "0");
+            let data: Value = self.client.send_json_request(url).await?;
+            let found = match data["body"]["works"] {
+                Value::Object(ref found) => found,
+                _ => return err::InvalidResponse { resp: data }.fail(),
+            };
+            // keep the order of ids, works deleted meanwhile are missing
+            for id in batch {
+                if let Some(work) = found.get(id) {
+                    let work = Work::from_json(work)?;
+                    if self.matches(&work) {
+                        works.push(work);
+                    }
+                }
+            }
+        }
+        Ok(works)
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for User {
+    async fn extract(&mut self) -> crate::FinaResult {
+        let title = self.name().await?;
+        let works = self.works().await?;
+        let extractions = works.into_iter().map(|work| {
+            let mut extractor = work.extractor(self.client.clone()).size(self.size);
+            async move {
+                match extractor.extract().await {
+                    // a work is always extracted into one origin
+                    Ok(playlist) => Ok(playlist.into_parts().0.swap_remove(0)),
+                    Err(e) if is_unavailable(&e) => {
+                        let mut origin = unavailable(work.title);
+                        origin.meta.insert("pid".to_owned(), work.id.into());
+                        Ok(origin)
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+        });
+        let origins = stream::iter(extractions)
+            .buffered(self.concurrency)
+            .try_collect()
+            .await?;
+        Ok(Playlist::new(origins, title))
+    }
+}
+
+impl AsClient for User {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
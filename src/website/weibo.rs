@@ -6,8 +6,9 @@ use reqwest::{
 use serde::Deserialize;
 
 use crate::{
+    error as err,
     utils::{self, Client},
-    Error, Extract, FinaResult, Finata, Origin, Track,
+    AsClient, Error, Extract, FinaResult, Finata, Origin, Track,
 };
 
 static HEADERS: Lazy<HeaderMap> = Lazy::new(|| {
@@ -17,40 +18,63 @@ static HEADERS: Lazy<HeaderMap> = Lazy::new(|| {
     }
 });
 
+/// `status` of a room which is streaming
+const LIVE_STATUS: u64 = 1;
+
+/// A extractor for the replay of a weibo live room, one video track per segment.
+/// See `LiveStream` for the stream while the room is live
 pub struct Live {
     id: String,
     client: Client,
 }
 
+/// A extractor for the stream of a weibo live room, as driven by `recorder::Recorder`.
+/// Reports `Error::LiveOffline` once the stream ends
+pub struct LiveStream {
+    id: String,
+    client: Client,
+}
+
+/// Parses the id of a live room from its url
+fn live_id(url: &str) -> Result<String, Error> {
+    let url = Url::parse(url)?;
+    match url
+        .path_segments()
+        .and_then(|mut p| p.rfind(|s| !s.is_empty()))
+    {
+        Some(id) => Ok(id.to_string()),
+        None => Err(Error::InvalidUrl { url }),
+    }
+}
+
+async fn room_json(client: &Client, id: &str) -> Result<ResponseData, Error> {
+    let url = format!(
+        "https://weibo.com/l/!/2/wblive/room/show_pc_live.json?live_id={}",
+        id
+    );
+    let response = client.send_json_request::<Response>(url.parse()?).await?;
+    Ok(response.data)
+}
+
 impl Live {
     pub fn new(url: &str) -> Result<Self, Error> {
-        let url = Url::parse(url)?;
-        match url
-            .path_segments()
-            .map(|p| p.filter(|s| !s.is_empty()).next_back())
-            .flatten()
-        {
-            Some(id) => Ok(Self {
-                id: dbg!(id).to_string(),
-                client: Client::with_header(HEADERS.clone()),
-            }),
-            None => Err(Error::InvalidUrl { url }),
-        }
+        Ok(Self {
+            id: live_id(url)?,
+            client: Client::with_header(HEADERS.clone()),
+        })
     }
 
     async fn _extract(&self) -> FinaResult {
-        let url = format!(
-            "https://weibo.com/l/!/2/wblive/room/show_pc_live.json?live_id={}",
-            self.id
-        );
-        let response = self
-            .client
-            .send_json_request::<Response>(url.parse()?)
-            .await?;
+        let data = room_json(&self.client, &self.id).await?;
+        if data.replay_origin_url.is_empty() {
+            return err::LiveOffline {
+                room: self.id.clone(),
+            }
+            .fail();
+        }
         let pl = self
             .client
-            .client()
-            .get(response.data.replay_origin_url)
+            .get(data.replay_origin_url.parse()?)
             .send()
             .await?
             .text()
@@ -63,9 +87,10 @@ impl Live {
             .map(|url| -> Result<_, Error> { Ok(Track::Video(url.parse()?)) })
             .collect::<Result<_, Error>>()?;
         let origin = Origin::new(tracks, String::new());
-        Ok(Finata::new(vec![origin], response.data.title))
+        Ok(Finata::new(vec![origin], data.title))
     }
 }
+
 #[async_trait::async_trait]
 impl Extract for Live {
     async fn extract(&mut self) -> crate::FinaResult {
@@ -73,8 +98,60 @@ impl Extract for Live {
     }
 }
 
+impl AsClient for Live {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
+
+impl LiveStream {
+    pub fn new(url: &str) -> Result<Self, Error> {
+        Ok(Self {
+            id: live_id(url)?,
+            client: Client::with_header(HEADERS.clone()),
+        })
+    }
+
+    async fn _extract(&self) -> FinaResult {
+        let data = room_json(&self.client, &self.id).await?;
+        // replays are served once the stream ends, which must not be taken as the stream
+        if data.status != LIVE_STATUS || data.live_origin_flv_url.is_empty() {
+            return err::LiveOffline {
+                room: self.id.clone(),
+            }
+            .fail();
+        }
+        let origin = Origin::video(data.live_origin_flv_url.parse()?, String::new());
+        Ok(Finata::new(vec![origin], data.title))
+    }
+}
+
+#[async_trait::async_trait]
+impl Extract for LiveStream {
+    async fn extract(&mut self) -> crate::FinaResult {
+        self._extract().await
+    }
+}
+
+impl AsClient for LiveStream {
+    fn client(&self) -> &Client {
+        &self.client
+    }
+    fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct ResponseData {
+    #[serde(default)]
+    pub status: u64,
+    #[serde(default)]
+    pub live_origin_flv_url: String,
+    #[serde(default)]
     pub replay_origin_url: String,
     pub title: String,
 }
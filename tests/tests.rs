@@ -75,6 +75,528 @@ mod bilibili {
         assert_eq!(res.title(), "《世界尽头的圣骑士》PV2");
         assert!(!res.raws().is_empty());
     }
+    #[tokio::test]
+    async fn season() {
+        let mut extractor = Bangumi::new("https://www.bilibili.com/bangumi/play/ss1512")
+            .unwrap()
+            .extracts_season(true);
+        *extractor.client_mut() = client();
+        let res = extractor.extract().await.unwrap();
+        assert!(res.raws().len() > 1);
+        assert!(res.raws().iter().all(|raw| !raw.title.is_empty()));
+        assert!(res.raws()[1].meta.contains_key("episode"));
+    }
+    #[tokio::test]
+    async fn space() {
+        let mut extractor = Space::new("https://space.bilibili.com/2/video")
+            .unwrap()
+            .order(Order::Click);
+        *extractor.client_mut() = client();
+        let uploads = extractor.uploads().await.unwrap();
+        assert!(!uploads.is_empty());
+    }
+    #[test]
+    fn list_urls() {
+        assert!(Favorites::new("https://www.bilibili.com/medialist/detail/ml1052622027").is_ok());
+        assert!(Favorites::new("https://space.bilibili.com/2/favlist?fid=1052622027").is_ok());
+        assert!(Favorites::new("https://www.bilibili.com/medialist/detail/").is_err());
+        assert!(
+            UgcSeason::new("https://space.bilibili.com/2/channel/collectiondetail?sid=1").is_ok()
+        );
+        assert!(Series::new("https://space.bilibili.com/2/lists/1?type=series").is_ok());
+        assert!(Series::new("https://space.bilibili.com/2/channel/seriesdetail").is_err());
+    }
+    #[test]
+    fn audio_urls() {
+        assert!(Audio::new("https://www.bilibili.com/audio/au1409763").is_ok());
+        assert!(Audio::new("https://m.bilibili.com/audio/au1409763/").is_ok());
+        assert!(Audio::new("https://www.bilibili.com/audio/am10624").is_err());
+        assert!(AudioMenu::new("https://www.bilibili.com/audio/am10624").is_ok());
+        assert!(
+            finata::website::choose_extractor("https://www.bilibili.com/audio/am10624").is_ok()
+        );
+    }
+    #[test]
+    fn course_urls() {
+        assert!(Course::new("https://www.bilibili.com/cheese/play/ss360").is_ok());
+        assert!(Course::new("https://www.bilibili.com/cheese/play/ep5802").is_ok());
+        assert!(Course::new("https://www.bilibili.com/cheese/play/md360").is_err());
+        assert!(
+            finata::website::choose_extractor("https://www.bilibili.com/cheese/play/ep5802")
+                .is_ok()
+        );
+    }
+    #[test]
+    fn interactive_graph() {
+        use interactive::{Graph, Node};
+        let data = serde_json::json!({
+            "title": "开始",
+            "edge_id": 1,
+            "edges": {"questions": [{
+                "id": 10, "type": 2, "title": "", "duration": -1,
+                "choices": [
+                    {"id": 2, "cid": 200, "option": "向左", "condition": "", "native_action": "$v1=$v1+1", "is_default": 1},
+                    {"id": 3, "cid": 300, "option": "向右", "condition": "$v1>=1", "native_action": ""},
+                ],
+            }]},
+        });
+        let root = Node::from_json(100, &data).unwrap();
+        assert_eq!(root.edge_id, 1);
+        assert_eq!(root.cid, 100);
+        let choices = root.choices().collect::<Vec<_>>();
+        assert_eq!(choices.len(), 2);
+        assert!(choices[0].is_default);
+        assert_eq!(choices[1].condition, "$v1>=1");
+        assert_eq!((choices[1].edge_id, choices[1].cid), (3, 300));
+        let leaf =
+            Node::from_json(200, &serde_json::json!({"title": "结局", "edge_id": 2})).unwrap();
+        assert!(leaf.is_leaf());
+        assert!(Node::from_json(0, &serde_json::json!({})).is_err());
+        let graph = Graph {
+            aid: 1,
+            graph_version: 1,
+            root: 1,
+            nodes: [(1, root), (2, leaf)].into(),
+            variables: Vec::new(),
+            truncated: false,
+        };
+        let json = serde_json::to_string(&graph).unwrap();
+        assert_eq!(serde_json::from_str::<Graph>(&json).unwrap(), graph);
+        // graphs saved without the flag are complete
+        let mut saved = serde_json::to_value(&graph).unwrap();
+        saved.as_object_mut().unwrap().remove("truncated");
+        assert!(!serde_json::from_value::<Graph>(saved).unwrap().truncated);
+    }
+    #[test]
+    fn dynamic_content() {
+        use dynamic::Content;
+        let url = |s: &str| url::Url::parse(s).unwrap();
+        let draw = serde_json::json!({
+            "type": "DYNAMIC_TYPE_DRAW",
+            "modules": {"module_dynamic": {
+                "desc": null,
+                "major": {"type": "MAJOR_TYPE_OPUS", "opus": {
+                    "summary": {"text": "新图"},
+                    "pics": [
+                        {"url": "http://i0.hdslb.com/bfs/new_dyn/a.jpg"},
+                        {"url": "https://i0.hdslb.com/bfs/new_dyn/b.png@1048w_!web-dynamic.webp"},
+                    ],
+                }},
+            }},
+        });
+        let images = Content::Images {
+            text: "新图".to_owned(),
+            images: vec![
+                url("https://i0.hdslb.com/bfs/new_dyn/a.jpg"),
+                url("https://i0.hdslb.com/bfs/new_dyn/b.png"),
+            ],
+        };
+        assert_eq!(Content::from_item(&draw).unwrap(), images);
+        let forward = serde_json::json!({"type": "DYNAMIC_TYPE_FORWARD", "orig": draw});
+        assert_eq!(Content::from_item(&forward).unwrap(), images);
+        let video = serde_json::json!({
+            "type": "DYNAMIC_TYPE_AV",
+            "modules": {"module_dynamic": {"major": {"archive": {"aid": "54592589", "bvid": "BV1L4411M7sC"}}}},
+        });
+        assert_eq!(
+            Content::from_item(&video).unwrap(),
+            Content::Video(Id::Bv("BV1L4411M7sC".to_owned()))
+        );
+        let article = serde_json::json!({
+            "type": "DYNAMIC_TYPE_ARTICLE",
+            "basic": {"rid_str": "1"},
+            "modules": {"module_dynamic": {"major": {"type": "MAJOR_TYPE_OPUS"}}},
+        });
+        assert_eq!(Content::from_item(&article).unwrap(), Content::Article(1));
+    }
+    #[test]
+    fn article_images() {
+        let data = serde_json::json!({
+            "content": "<p>文字</p><figure><img data-src=\"//i0.hdslb.com/bfs/article/a.jpg\" width=\"1\"></figure>\
+                <img src=\"https://i0.hdslb.com/bfs/article/b.gif@progressive.webp\" />",
+        });
+        let images = dynamic::article_images(&data).unwrap();
+        assert_eq!(
+            images.iter().map(url::Url::as_str).collect::<Vec<_>>(),
+            [
+                "https://i0.hdslb.com/bfs/article/a.jpg",
+                "https://i0.hdslb.com/bfs/article/b.gif"
+            ]
+        );
+        let opus = serde_json::json!({"content": "", "opus": {"content": {"paragraphs": [
+            {"para_type": 1, "text": {"nodes": []}},
+            {"para_type": 2, "pic": {"pics": [{"url": "https://i0.hdslb.com/bfs/article/c.png"}]}},
+        ]}}});
+        assert_eq!(dynamic::article_images(&opus).unwrap().len(), 1);
+        assert!(Article::new("https://www.bilibili.com/read/cv1").is_ok());
+        assert!(Dynamic::new("https://t.bilibili.com/915349412549509124").is_ok());
+        assert!(Dynamic::new("https://www.bilibili.com/opus/915349412549509124").is_ok());
+        assert!(Dynamic::new("https://m.bilibili.com/dynamic/915349412549509124").is_ok());
+        assert!(Dynamic::new("https://www.bilibili.com/video/915349412549509124").is_err());
+        assert!(Dynamic::new("https://t.bilibili.com/a/915349412549509124").is_err());
+        assert!(finata::website::choose_extractor(
+            "https://m.bilibili.com/dynamic/915349412549509124"
+        )
+        .is_ok());
+        assert!(finata::website::choose_extractor("https://www.bilibili.com/read/cv1").is_ok());
+        assert!(finata::website::choose_extractor("https://t.bilibili.com/1").is_ok());
+    }
+    #[test]
+    fn manga_urls() {
+        assert!(Manga::new("https://manga.bilibili.com/mc26742/351011").is_ok());
+        assert!(Manga::new("https://manga.bilibili.com/detail/mc26742").is_ok());
+        assert!(Manga::new("https://manga.bilibili.com/detail/").is_err());
+        assert!(
+            finata::website::choose_extractor("https://manga.bilibili.com/mc26742/351011").is_ok()
+        );
+    }
+    #[test]
+    fn av_bv_conversion() {
+        assert_eq!(bv::av_to_bv(170001).unwrap(), "BV17x411w7KC");
+        assert_eq!(bv::av_to_bv(54592589).unwrap(), "BV1L4411M7sC");
+        assert_eq!(bv::bv_to_av("BV1xx411c7mQ"), Some(1));
+        assert_eq!(bv::bv_to_av("BV1L4411M7sC"), Some(54592589));
+        // aids larger than 2^32
+        let large = (1 << 51) - 1;
+        assert_eq!(bv::bv_to_av(&bv::av_to_bv(large).unwrap()), Some(large));
+        assert_eq!(bv::av_to_bv(1 << 51), None);
+        assert_eq!(bv::av_to_bv(0), None);
+    }
+    #[test]
+    fn bv_validation() {
+        assert!(bv::is_valid_bv("BV1L4411M7sC"));
+        assert!(!bv::is_valid_bv("BV1L4411M7s"));
+        assert!(!bv::is_valid_bv("BV1L4411M7s0"));
+        assert!(!bv::is_valid_bv("AV1L4411M7sC"));
+        assert!(Id::new("BV1L4411M7s0").is_none());
+    }
+    #[test]
+    fn id_normalization() {
+        let av = Id::new("av54592589").unwrap();
+        let bv = Id::new("BV1L4411M7sC").unwrap();
+        assert_eq!(av.bvid().unwrap(), "BV1L4411M7sC");
+        assert_eq!(bv.aid(), Some(54592589));
+        assert_eq!(av.normalize(), bv.normalize());
+        assert_eq!(Id::Ep(1).normalize(), Id::Ep(1));
+    }
+    #[test]
+    fn live_stream_options() {
+        let extractor = BaseLiveExtractor::new(1, client())
+            .with_stream(StreamOptions::hls(Format::Fmp4).codec(Codec::Hevc).qn(400));
+        let url = url::Url::parse(&extractor.as_live_url()).unwrap();
+        let query = url
+            .query_pairs()
+            .collect::<std::collections::HashMap<_, _>>();
+        assert_eq!(query["room_id"], "1");
+        assert_eq!(query["protocol"], "1");
+        assert_eq!(query["format"], "2");
+        assert_eq!(query["codec"], "1");
+        assert_eq!(query["qn"], "400");
+    }
+    #[test]
+    fn live_codec_fallback() {
+        let data = serde_json::json!({ "data": { "room_id": 1, "playurl_info": { "playurl": { "stream": [{
+            "protocol_name": "http_stream",
+            "format": [{ "format_name": "flv", "codec": [{
+                "codec_name": "avc", "current_qn": 10000, "accept_qn": [10000],
+                "base_url": "/live/1.flv", "url_info": [{ "host": "https://cn.example.com", "extra": "?t=1" }],
+            }] }],
+        }] } } } });
+        let hevc = StreamOptions::default().codec(Codec::Hevc);
+        let origin = live::play_origin(&data, &hevc).unwrap();
+        assert_eq!(
+            origin.tracks[0].as_url().as_str(),
+            "https://cn.example.com/live/1.flv?t=1"
+        );
+        // the codec served is reported instead of the one requested
+        assert_eq!(origin.meta["codec"], "avc");
+        assert_eq!(origin.meta["format"], "flv");
+        let hls = StreamOptions::hls(Format::Ts);
+        assert!(live::play_origin(&data, &hls).is_err());
+    }
+    #[test]
+    fn dash_streams() {
+        use dash::{AudioFormat, DynamicRange, Variant};
+        let stream = |id, codecs| {
+            serde_json::json!({
+                "id": id, "baseUrl": format!("https://upos.example.com/{}.m4s", id),
+                "codecs": codecs, "bandwidth": id * 100,
+            })
+        };
+        let info = serde_json::json!({
+            "video": [
+                stream(80, "avc1.640032"),
+                stream(125, "hev1.2.4.L153.90"),
+                stream(80, "hev1.1.6.L150.90"),
+                stream(126, "dvh1.08.07"),
+            ],
+            "audio": [stream(30216, "mp4a.40.2"), stream(30280, "mp4a.40.2")],
+            "dolby": {"type": 2, "audio": [stream(30250, "ec-3")]},
+            "flac": {"display": true, "audio": stream(30251, "fLaC")},
+        });
+        let streams = dash::streams(&info).unwrap();
+        let labels = streams.iter().map(|s| s.label()).collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            [
+                "Dolby Vision",
+                "HDR",
+                "1080P",
+                "1080P",
+                "Hi-Res FLAC",
+                "Dolby Atmos",
+                "192K",
+                "64K"
+            ]
+        );
+        assert_eq!(
+            streams[0].variant,
+            Variant::Video(DynamicRange::DolbyVision)
+        );
+        assert_eq!(streams[1].variant, Variant::Video(DynamicRange::Hdr));
+        assert_eq!(streams[2].variant, Variant::Video(DynamicRange::Sdr));
+        assert_eq!(streams[2].codecs, "avc1.640032");
+        assert_eq!(streams[4].variant, Variant::Audio(AudioFormat::HiResFlac));
+        assert!(matches!(streams[4].track, finata::Track::Audio(_)));
+        assert_eq!(streams[4].to_json()["variant"], "flac");
+        // no flac or dolby for most videos
+        let info = serde_json::json!({
+            "video": [stream(64, "avc1.64001F")],
+            "audio": [stream(30280, "mp4a.40.2")],
+            "dolby": {"type": 0, "audio": null},
+            "flac": null,
+        });
+        assert_eq!(dash::streams(&info).unwrap().len(), 2);
+        assert_eq!(dash::FNVAL_ALL, 4048);
+    }
+    #[test]
+    fn monitor_events() {
+        use monitor::{Event, RoomStatus};
+        let status = |live_status, title| -> RoomStatus {
+            serde_json::from_value(serde_json::json!({
+                "uid": 2, "room_id": 1, "uname": "碧诗", "title": title, "live_status": live_status,
+            }))
+            .unwrap()
+        };
+        let (offline, live, renamed) = (status(0, "a"), status(1, "a"), status(1, "b"));
+        assert_eq!(Event::between(None, &offline), None);
+        assert_eq!(
+            Event::between(None, &live),
+            Some(Event::WentLive(live.clone()))
+        );
+        assert_eq!(
+            Event::between(Some(&offline), &live),
+            Some(Event::WentLive(live.clone()))
+        );
+        assert_eq!(Event::between(Some(&live), &live), None);
+        assert_eq!(
+            Event::between(Some(&live), &renamed),
+            Some(Event::TitleChanged {
+                old: "a".to_owned(),
+                status: renamed.clone()
+            })
+        );
+        // replaying videos counts as offline
+        let replaying = status(2, "b");
+        assert_eq!(
+            Event::between(Some(&renamed), &replaying),
+            Some(Event::WentOffline(replaying.clone()))
+        );
+        assert_eq!(Event::between(Some(&replaying), &status(0, "b")), None);
+    }
+    #[tokio::test]
+    async fn monitor() {
+        let statuses = Monitor::with_client(client())
+            .uid(2)
+            .room(1)
+            .poll()
+            .await
+            .unwrap();
+        assert_eq!(statuses[&2].room_id, 1);
+    }
+
+    fn danmaku_frame() -> Vec<u8> {
+        use danmaku::*;
+        let danmu = serde_json::json!({
+            "cmd": "DANMU_MSG:4:0:2:2:2:0",
+            "info": [[0, 1, 25, 16777215], "草", [12345, "someone", 0, 0, 0, 10000, 1, ""]],
+        });
+        let gift = serde_json::json!({
+            "cmd": "SEND_GIFT",
+            "data": {"uid": 6789, "uname": "another", "giftName": "辣条", "num": 3, "price": 100},
+        });
+        let mut frame =
+            Packet::new(PROTOVER_JSON, OP_MESSAGE, danmu.to_string().into_bytes()).encode();
+        frame
+            .extend(Packet::new(PROTOVER_JSON, OP_MESSAGE, gift.to_string().into_bytes()).encode());
+        frame.extend(
+            Packet::new(
+                PROTOVER_INT,
+                OP_HEARTBEAT_REPLY,
+                42u32.to_be_bytes().to_vec(),
+            )
+            .encode(),
+        );
+        frame
+    }
+    fn danmaku_events() -> Vec<danmaku::Event> {
+        vec![
+            danmaku::Event::Danmaku {
+                uid: 12345,
+                uname: "someone".to_owned(),
+                text: "草".to_owned(),
+            },
+            danmaku::Event::Gift {
+                uid: 6789,
+                uname: "another".to_owned(),
+                gift: "辣条".to_owned(),
+                num: 3,
+                price: 100,
+            },
+            danmaku::Event::Popularity(42),
+        ]
+    }
+    fn decode_events(frame: &[u8]) -> Vec<danmaku::Event> {
+        danmaku::Packet::decode(frame)
+            .unwrap()
+            .iter()
+            .filter_map(danmaku::Event::from_packet)
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+    fn brotli_frame() -> Vec<u8> {
+        use std::io::Write;
+        let mut compressed = Vec::new();
+        {
+            let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
+            writer.write_all(&danmaku_frame()).unwrap();
+        }
+        danmaku::Packet::new(danmaku::PROTOVER_BROTLI, danmaku::OP_MESSAGE, compressed).encode()
+    }
+    #[test]
+    fn danmaku_encode() {
+        assert_eq!(
+            danmaku::Packet::heartbeat().encode(),
+            [0, 0, 0, 16, 0, 16, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1]
+        );
+        let auth = danmaku::Packet::auth(1, 0, "token");
+        let decoded = danmaku::Packet::decode(&auth.encode()).unwrap();
+        assert_eq!(decoded, [auth]);
+    }
+    #[test]
+    fn danmaku_decode_plain() {
+        assert_eq!(decode_events(&danmaku_frame()), danmaku_events());
+    }
+    #[test]
+    fn danmaku_decode_zlib() {
+        use std::io::Write;
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&danmaku_frame()).unwrap();
+        let frame = danmaku::Packet::new(
+            danmaku::PROTOVER_ZLIB,
+            danmaku::OP_MESSAGE,
+            encoder.finish().unwrap(),
+        )
+        .encode();
+        assert_eq!(decode_events(&frame), danmaku_events());
+    }
+    #[test]
+    fn danmaku_decode_brotli() {
+        assert_eq!(decode_events(&brotli_frame()), danmaku_events());
+    }
+    #[test]
+    fn danmaku_decode_truncated() {
+        let frame = danmaku_frame();
+        assert!(danmaku::Packet::decode(&frame[..frame.len() - 1]).is_err());
+        assert!(danmaku::Packet::decode(&frame[..10]).is_err());
+    }
+    #[tokio::test]
+    async fn danmaku_stand_in_server() {
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let auth = match ws.next().await {
+                Some(Ok(Message::Binary(buf))) => danmaku::Packet::decode(&buf).unwrap(),
+                msg => panic!("unexpected message {:?}", msg),
+            };
+            assert_eq!(auth[0].op, danmaku::OP_AUTH);
+            let body: serde_json::Value = serde_json::from_slice(&auth[0].body).unwrap();
+            assert_eq!(body["roomid"], 1);
+            assert_eq!(body["key"], "token");
+            let reply = danmaku::Packet::new(
+                danmaku::PROTOVER_INT,
+                danmaku::OP_AUTH_REPLY,
+                br#"{"code":0}"#.to_vec(),
+            );
+            ws.send(Message::Binary(reply.encode())).await.unwrap();
+            // heartbeat is sent right after auth
+            match ws.next().await {
+                Some(Ok(Message::Binary(buf))) => {
+                    assert_eq!(
+                        danmaku::Packet::decode(&buf).unwrap()[0].op,
+                        danmaku::OP_HEARTBEAT
+                    )
+                }
+                msg => panic!("unexpected message {:?}", msg),
+            }
+            ws.send(Message::Binary(brotli_frame())).await.unwrap();
+            ws.close(None).await.unwrap();
+        });
+        let url = url::Url::parse(&format!("ws://{}/sub", addr)).unwrap();
+        let events = danmaku::connect(&url, 1, 0, "token")
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(events, danmaku_events());
+        server.await.unwrap();
+    }
+
+    fn wbi_keys() -> wbi::WbiKeys {
+        wbi::WbiKeys::new(
+            "7cd084941338484aae1ad9425b84077c".to_owned(),
+            "4932caff0ff746eab6f01bf08b70ac45".to_owned(),
+        )
+    }
+    #[test]
+    fn wbi_mixin_key() {
+        assert_eq!(wbi_keys().mixin_key(), "ea1db124af3c7062474693fa704f4ff8");
+    }
+    #[test]
+    fn wbi_sign() {
+        let query = wbi_keys().sign_with_wts(
+            &[("foo", "114"), ("bar", "514"), ("zab", "1919810")],
+            1702204169,
+        );
+        assert_eq!(
+            query,
+            "bar=514&foo=114&wts=1702204169&zab=1919810&w_rid=8f6f2b5b3d485fe1886cec6a0be8c5d4"
+        );
+    }
+    #[test]
+    fn wbi_sign_escapes() {
+        let query = wbi_keys().sign_with_wts(&[("keyword", "a b!(c)*'")], 0);
+        assert!(query.starts_with("keyword=a%20bc&wts=0&w_rid="));
+    }
+    #[test]
+    fn wbi_sign_url() {
+        let url = url::Url::parse("https://example.com/api?mid=2&w_rid=0&wts=1").unwrap();
+        let signed = wbi_keys().sign_url(&url);
+        let keys = signed
+            .query_pairs()
+            .map(|(k, _)| k.into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, ["mid", "wts", "w_rid"]);
+    }
 }
 
 #[cfg(test)]
@@ -115,6 +637,10 @@ mod netease_music {
 #[cfg(test)]
 mod pixiv {
     use finata::{website::pixiv::*, Extract};
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
 
     #[tokio::test]
     async fn pixiv() {
@@ -123,4 +649,987 @@ mod pixiv {
         assert_eq!(res.title(), "「とじこめて」");
         assert_eq!(res.raws()[0].tracks.len(), 2);
     }
+
+    #[test]
+    fn links() {
+        let parse = |s: &str| Link::parse(&s.parse().unwrap());
+        let artwork = Link::Artwork("92386069".to_owned());
+        for s in [
+            "https://www.pixiv.net/artworks/92386069",
+            "https://www.pixiv.net/en/artworks/92386069/",
+            "https://www.pixiv.net/artworks/92386069#manga",
+            "https://pixiv.net/i/92386069",
+            "https://www.pixiv.net/member_illust.php?mode=medium&illust_id=92386069",
+            "http://touch.pixiv.net/member_illust.php?illust_id=92386069&mode=manga",
+            "https://i.pximg.net/img-original/img/2021/08/25/00/00/09/92386069_p0.png",
+            "https://i.pximg.net/c/250x250_80_a2/img-master/img/2021/08/25/00/00/09/92386069_p1_square1200.jpg",
+            "https://i.pximg.net/img-zip-ugoira/img/2021/08/25/00/00/09/92386069_ugoira600x600.zip",
+        ] {
+            assert_eq!(parse(s).unwrap(), artwork, "{}", s);
+        }
+        let user = |kinds: Vec<WorkType>| Link::User {
+            uid: "11".to_owned(),
+            kinds,
+        };
+        assert_eq!(
+            parse("https://www.pixiv.net/users/11").unwrap(),
+            user(vec![])
+        );
+        assert_eq!(
+            parse("https://www.pixiv.net/en/users/11/artworks").unwrap(),
+            user(vec![])
+        );
+        assert_eq!(
+            parse("https://www.pixiv.net/users/11/illustrations").unwrap(),
+            user(vec![WorkType::Illust, WorkType::Ugoira])
+        );
+        assert_eq!(
+            parse("https://www.pixiv.net/zh-tw/users/11/manga").unwrap(),
+            user(vec![WorkType::Manga])
+        );
+        assert_eq!(
+            parse("https://www.pixiv.net/member.php?id=11").unwrap(),
+            user(vec![])
+        );
+        assert_eq!(
+            parse("https://www.pixiv.net/member_illust.php?id=11").unwrap(),
+            user(vec![])
+        );
+        let bookmarks = |private| Link::Bookmarks {
+            uid: "11".to_owned(),
+            private,
+        };
+        assert_eq!(
+            parse("https://www.pixiv.net/users/11/bookmarks/artworks").unwrap(),
+            bookmarks(false)
+        );
+        assert_eq!(
+            parse("https://www.pixiv.net/en/users/11/bookmarks/artworks?rest=hide").unwrap(),
+            bookmarks(true)
+        );
+        assert_eq!(
+            parse("https://www.pixiv.net/bookmark.php?id=11&rest=show").unwrap(),
+            bookmarks(false)
+        );
+        assert_eq!(
+            parse("https://www.pixiv.net/user/11/series/7").unwrap(),
+            Link::Series("7".to_owned())
+        );
+        assert_eq!(
+            parse("https://www.pixiv.net/novel/show.php?id=5").unwrap(),
+            Link::Novel("5".to_owned())
+        );
+        assert_eq!(
+            parse("https://www.pixiv.net/novel/series/5").unwrap(),
+            Link::NovelSeries("5".to_owned())
+        );
+        for s in [
+            "https://www.pixiv.net/",
+            "https://www.pixiv.net/artworks/abc",
+            "https://www.pixiv.net/users/11/following",
+            "https://www.pixiv.net/users/11/bookmarks/novels",
+            "https://www.pixiv.net/bookmark.php",
+            "https://www.pixiv.net/member_illust.php?mode=medium",
+            "https://pixiv.me/someone",
+            "https://i.pximg.net/user-profile/img/2021/01/01/00/00/00/abc_170.png",
+            "https://i.pximg.net/user-profile/img/2021/01/01/00/00/00/20000000_0123456789abcdef0123456789abcdef_170.jpg",
+            "https://i.pximg.net/background/img/2021/01/01/00/00/00/11_0123456789abcdef.jpg",
+            "https://example.com/artworks/92386069",
+        ] {
+            assert!(
+                matches!(parse(s), Err(finata::Error::InvalidUrl { .. })),
+                "{}",
+                s
+            );
+        }
+        assert!(Pixiv::new("https://www.pixiv.net/users/11").is_err());
+        assert!(Collection::new("https://www.pixiv.net/users/11/bookmarks/artworks").is_ok());
+        assert!(finata::website::choose_extractor("https://www.pixiv.net/i/92386069").is_ok());
+        assert!(finata::website::choose_extractor("https://www.pixiv.net/tags/a").is_err());
+    }
+
+    #[test]
+    fn ranking() {
+        use ranking::{ranking_page, Content, Mode};
+
+        let data = serde_json::json!({
+            "contents": [
+                { "rank": 1, "illust_id": 92386069, "title": "「とじこめて」", "illust_type": "0" },
+                { "rank": 2, "illust_id": 100, "title": "b", "illust_type": "1" },
+            ],
+            "mode": "daily",
+            "content": "all",
+            "date": "20240101",
+            "next": 2,
+        });
+        let (entries, next) = ranking_page(&data).unwrap();
+        assert_eq!(next, Some(2));
+        assert_eq!(entries[0].pid, "92386069");
+        assert_eq!(entries[1].rank, 2);
+        let last = serde_json::json!({ "contents": [], "next": false });
+        assert_eq!(ranking_page(&last).unwrap().1, None);
+        assert!(ranking_page(&serde_json::json!({ "error": "" })).is_err());
+
+        assert_eq!(Mode::parse("weekly_r18"), Some(Mode::WeeklyR18));
+        assert!(Mode::parse("weekly_r18").unwrap().is_r18());
+        assert!(!Mode::Rookie.is_r18());
+        assert_eq!(Content::parse("ugoira"), Some(Content::Ugoira));
+        assert!(Ranking::new("https://www.pixiv.net/ranking.php").is_ok());
+        assert!(Ranking::new(
+            "https://www.pixiv.net/ranking.php?mode=male_r18&content=illust&date=20240101"
+        )
+        .is_ok());
+        assert!(Ranking::new("https://www.pixiv.net/ranking.php?mode=yearly").is_err());
+        assert!(Ranking::new("https://www.pixiv.net/artworks/1").is_err());
+        assert!(
+            finata::website::choose_extractor("https://www.pixiv.net/ranking.php?mode=weekly")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn image_sizes() {
+        let page = serde_json::json!({
+            "urls": {
+                "thumb_mini": "https://i.pximg.net/c/128x128/img-master/1_p0_square1200.jpg",
+                "small": "https://i.pximg.net/c/540x540_70/img-master/1_p0_master1200.jpg",
+                "regular": "https://i.pximg.net/img-master/1_p0_master1200.jpg",
+                "original": "https://i.pximg.net/img-original/1_p0.png",
+            },
+            "width": 2400,
+            "height": 1800,
+        });
+        let (url, image) = page_image(&page, Size::default()).unwrap();
+        assert!(url.path().starts_with("/img-original/"));
+        assert_eq!(image["width"], 2400);
+        let (url, image) = page_image(&page, Size::Small).unwrap();
+        assert!(url.path().starts_with("/c/540x540_70/"));
+        assert_eq!(image["size"], "small");
+        assert_eq!(
+            (image["width"].as_u64(), image["height"].as_u64()),
+            (Some(540), Some(405))
+        );
+        assert_eq!(Size::Regular.fit(600, 800), (600, 800));
+        assert_eq!(Size::ThumbMini.fit(600, 800), (128, 128));
+        assert!(page_image(&serde_json::json!({ "urls": {} }), Size::Regular).is_err());
+    }
+
+    #[test]
+    fn artwork_metadata() {
+        let body = serde_json::json!({
+            "illustId": "92386069",
+            "illustType": 0,
+            "userId": "3",
+            "userName": "a",
+            "tags": { "tags": [
+                { "tag": "オリジナル", "translation": { "en": "original" } },
+                { "tag": "女の子" },
+            ] },
+            "xRestrict": 2,
+            "aiType": 1,
+            "createDate": "2021-08-25T00:00:09+09:00",
+            "uploadDate": "2021-08-25T00:00:09+09:00",
+            "pageCount": 2,
+            "bookmarkCount": 10,
+            "likeCount": 5,
+            "viewCount": 100,
+            "description": "<br />",
+        });
+        let meta = artwork_meta(&body);
+        assert_eq!(meta["tags"][0]["translation"], "original");
+        assert!(meta["tags"][1].get("translation").is_none());
+        assert_eq!(meta["restriction"], "R-18G");
+        assert_eq!(meta["ai_generated"], false);
+        assert_eq!(meta["bookmark_count"], 10);
+        assert_eq!(meta["user_name"], "a");
+        let meta = artwork_meta(&serde_json::json!({ "xRestrict": 0, "aiType": 0 }));
+        assert_eq!(meta["restriction"], Restriction::General.as_str());
+        assert!(meta["ai_generated"].is_null());
+    }
+
+    #[test]
+    fn bookmarks() {
+        let bookmark = Bookmark::from_json(&serde_json::json!({
+            "id": "92386069",
+            "title": "「とじこめて」",
+            "createDate": "2021-08-25T00:00:09+09:00",
+        }))
+        .unwrap();
+        assert_eq!(bookmark.id, "92386069");
+        assert_eq!(bookmark.create_date, "2021-08-25T00:00:09+09:00");
+        // deleted works keep their ids as numbers
+        assert_eq!(
+            Bookmark::from_json(&serde_json::json!({ "id": 1, "isMasked": true }))
+                .unwrap()
+                .id,
+            "1"
+        );
+        assert!(Bookmark::from_json(&serde_json::json!({})).is_err());
+    }
+
+    /// Stand-in of the bookmarks api, where user 1 has bookmarked works 1 to 200, 20 per month
+    /// from 2021-01 in order, and user 2 does not exist. Requested offsets and limits are recorded
+    async fn bookmarks_stand_in(logged_in: bool) -> (url::Url, Arc<Mutex<Vec<(u64, u64)>>>) {
+        let requested = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requested.clone();
+        let base = crate::stand_in::serve(move |req| {
+            let url = url::Url::parse(&format!("http://stand-in{}", req.path)).unwrap();
+            let query = url.query_pairs().into_owned().collect::<HashMap<_, _>>();
+            let body = match url.path() {
+                "/extra" => serde_json::json!({ "error": !logged_in, "body": {} }),
+                "/1/illusts/bookmarks" if logged_in => {
+                    let offset: u64 = query["offset"].parse().unwrap();
+                    let limit: u64 = query["limit"].parse().unwrap();
+                    requested.lock().unwrap().push((offset, limit));
+                    let works = (1..=200u64)
+                        .rev()
+                        .skip(offset as usize)
+                        .take(limit as usize)
+                        .map(|id| {
+                            serde_json::json!({
+                                "id": id.to_string(),
+                                "title": "",
+                                "createDate": format!("2021-{:02}-01T00:00:00+09:00", (id - 1) / 20 + 1),
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    serde_json::json!({ "error": false, "body": { "works": works, "total": 200 } })
+                }
+                _ => serde_json::json!({ "error": true, "message": "", "body": [] }),
+            };
+            Some(body.to_string().into_bytes())
+        })
+        .await;
+        (format!("{}/", base).parse().unwrap(), recorded)
+    }
+
+    fn ids(bookmarks: &[Bookmark]) -> Vec<&str> {
+        bookmarks.iter().map(|b| b.id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn bookmarks_window() {
+        let (api, requested) = bookmarks_stand_in(true).await;
+        let all = Collection::with_uid("1".to_owned())
+            .api(api.clone())
+            .bookmarks()
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 200);
+        assert_eq!((all[0].id.as_str(), all[199].id.as_str()), ("200", "1"));
+        assert_eq!(
+            std::mem::take(&mut *requested.lock().unwrap()),
+            [(0, 96), (96, 96), (192, 96)]
+        );
+
+        let window = Collection::with_uid("1".to_owned())
+            .api(api.clone())
+            .offset(10)
+            .max(100)
+            .bookmarks()
+            .await
+            .unwrap();
+        assert_eq!(window.len(), 100);
+        assert_eq!(ids(&window)[..2], ["190", "189"]);
+        assert_eq!(ids(&window)[99], "91");
+        assert_eq!(
+            std::mem::take(&mut *requested.lock().unwrap()),
+            [(10, 96), (106, 4)]
+        );
+
+        // dates are filtered after `max` is counted
+        let dated = Collection::with_uid("1".to_owned())
+            .api(api.clone())
+            .since("2021-03-01")
+            .until("2021-04-30")
+            .bookmarks()
+            .await
+            .unwrap();
+        assert_eq!(dated.len(), 40);
+        assert_eq!((dated[0].id.as_str(), dated[39].id.as_str()), ("80", "41"));
+        let dated = Collection::with_uid("1".to_owned())
+            .api(api.clone())
+            .max(150)
+            .since("2021-03-01")
+            .bookmarks()
+            .await
+            .unwrap();
+        assert_eq!(ids(&dated)[..2], ["200", "199"]);
+        assert_eq!(ids(&dated).last(), Some(&"51"));
+    }
+
+    #[tokio::test]
+    async fn bookmarks_errors() {
+        let (api, _) = bookmarks_stand_in(true).await;
+        let res = Collection::with_uid("2".to_owned())
+            .api(api)
+            .bookmarks()
+            .await;
+        assert!(matches!(res, Err(finata::Error::InvalidResponse { .. })));
+        let (api, _) = bookmarks_stand_in(false).await;
+        let res = Collection::with_uid("1".to_owned())
+            .api(api)
+            .bookmarks()
+            .await;
+        assert!(matches!(res, Err(finata::Error::LoginRequired { .. })));
+    }
+
+    #[test]
+    fn user_works() {
+        let body = serde_json::json!({
+            "illusts": { "100": null, "300": null },
+            "manga": { "200": null },
+            "novels": { "400": null },
+        });
+        assert_eq!(user::profile_ids(&body).unwrap(), ["300", "200", "100"]);
+        let body = serde_json::json!({ "illusts": [], "manga": [] });
+        assert!(user::profile_ids(&body).unwrap().is_empty());
+        assert!(user::profile_ids(&serde_json::json!({})).is_err());
+
+        let work = user::Work::from_json(&serde_json::json!({
+            "id": "300",
+            "title": "a",
+            "illustType": 2,
+            "tags": ["オリジナル", "うごイラ"],
+            "userName": "b",
+        }))
+        .unwrap();
+        assert_eq!(work.kind, WorkType::Ugoira);
+        assert_eq!(work.tags.len(), 2);
+        assert_eq!(work.user_name, "b");
+
+        assert!(User::new("https://www.pixiv.net/users/11").is_ok());
+        assert!(User::new("https://www.pixiv.net/en/users/11/manga").is_ok());
+        assert!(User::new("https://www.pixiv.net/users/abc").is_err());
+    }
+
+    #[test]
+    fn series_chapters() {
+        let body = serde_json::json!({
+            "illustSeries": [{ "id": "7", "title": "s", "total": 13 }],
+            "page": {
+                "series": [
+                    { "workId": "102", "order": 2 },
+                    { "workId": "101", "order": 1 },
+                ],
+                "total": 13,
+            },
+            "thumbnails": { "illust": [{ "id": "101", "title": "第1話" }] },
+        });
+        let (chapters, total) = series::series_page(&body).unwrap();
+        assert_eq!(total, 13);
+        assert_eq!(chapters[1].order, 1);
+        assert_eq!(chapters[1].title, "第1話");
+        assert_eq!(chapters[0].title, "");
+        assert!(series::series_page(&serde_json::json!({})).is_err());
+
+        assert!(Series::new("https://www.pixiv.net/user/3/series/7").is_ok());
+        assert!(Series::new("https://www.pixiv.net/user/3/series/").is_err());
+    }
+
+    #[test]
+    fn novel_markup() {
+        use novel::{ImageRef, Segment};
+
+        let body = serde_json::json!({
+            "title": "t",
+            "userName": "u",
+            "content": "[chapter:[[rb:序 > じょ]]章]\n[[rb:漢字 > かんじ]]と[x][[jumpuri:リンク > https://example.com]][jump:2]\n[newpage]\n[uploadedimage:5][pixivimage:9-2]",
+            "textEmbeddedImages": { "5": { "urls": { "original": "https://i.pximg.net/5.png" } } },
+        });
+        let document = novel::Document::from_json(&body).unwrap();
+        assert_eq!(document.segments[0], Segment::Chapter("序章".to_owned()));
+        assert_eq!(
+            document.segments[2],
+            Segment::Ruby {
+                base: "漢字".to_owned(),
+                ruby: "かんじ".to_owned()
+            }
+        );
+        assert_eq!(document.segments[3], Segment::Text("と[x]".to_owned()));
+        assert_eq!(document.pages().count(), 2);
+        assert_eq!(
+            document.image_refs(),
+            [
+                &ImageRef::Uploaded("5".to_owned()),
+                &ImageRef::Illust {
+                    id: "9".to_owned(),
+                    page: 2
+                }
+            ]
+        );
+        let md = document.to_markdown();
+        assert!(md.starts_with("\n## 序章\n"));
+        assert!(
+            md.contains("<ruby>漢字<rt>かんじ</rt></ruby>と\\[x\\][リンク](https://example.com)\n")
+        );
+        assert!(md.contains("\n---\n"));
+        assert!(md.ends_with("![](https://i.pximg.net/5.png)"));
+
+        // text is never taken as markdown syntax
+        let body = serde_json::json!({
+            "content": "# a *b* _c_\n- d > e\n1. f\n　> g\n[chapter:h_i]\n[[jumpuri:[j] > https://example.com/k (l)]]",
+        });
+        let md = novel::Document::from_json(&body).unwrap().to_markdown();
+        assert_eq!(
+            md,
+            "\\# a \\*b\\* \\_c\\_\n\\- d > e\n1\\. f\n　\\> g\n\n## h\\_i\n\n[\\[j\\]](https://example.com/k%20\\(l\\))"
+        );
+
+        let contents = serde_json::json!({ "page": { "seriesContents": [
+            { "id": "11", "series": { "contentOrder": 1 } },
+        ] } });
+        assert_eq!(
+            novel::series_contents(&contents).unwrap(),
+            [("11".to_owned(), 1)]
+        );
+        assert!(Novel::new("https://www.pixiv.net/novel/show.php?id=11").is_ok());
+        assert!(Novel::new("https://www.pixiv.net/novel/show.php").is_err());
+        assert!(NovelSeries::new("https://www.pixiv.net/novel/series/3").is_ok());
+    }
+
+    #[cfg(feature = "epub")]
+    #[tokio::test]
+    async fn novel_epub() {
+        use std::io::Read;
+
+        // images without extensions in their names
+        let base = crate::stand_in::serve(|req| match req.path.as_str() {
+            "/c/600x600/5" => Some(b"\x89PNG".to_vec()),
+            _ => None,
+        })
+        .await;
+        let body = serde_json::json!({
+            "title": "a&b",
+            "userName": "u",
+            "language": "zh-cn",
+            "content": "[chapter:一]\n<本文>\n\n[newpage]\n[chapter:二][uploadedimage:5]",
+            "textEmbeddedImages": { "5": { "urls": { "original": format!("{}/c/600x600/5", base) } } },
+        });
+        let document = novel::Document::from_json(&body).unwrap();
+        let client = finata::utils::Client::new();
+        let epub = document.to_epub(&client).await.unwrap();
+        let mut zip = zip::ZipArchive::new(std::io::Cursor::new(epub)).unwrap();
+        assert_eq!(zip.by_index(0).unwrap().name(), "mimetype");
+        let mut page = String::new();
+        zip.by_name("OEBPS/page1.xhtml")
+            .unwrap()
+            .read_to_string(&mut page)
+            .unwrap();
+        assert!(page.contains("<h2 id=\"c1\">一</h2>"));
+        assert!(page.contains("<p>&lt;本文&gt;</p>"));
+        let mut nav = String::new();
+        zip.by_name("OEBPS/nav.xhtml")
+            .unwrap()
+            .read_to_string(&mut nav)
+            .unwrap();
+        assert!(nav.contains("page2.xhtml#c2"));
+        let mut opf = String::new();
+        zip.by_name("OEBPS/content.opf")
+            .unwrap()
+            .read_to_string(&mut opf)
+            .unwrap();
+        assert!(opf.contains("<dc:language>zh-cn</dc:language>"));
+        assert!(opf.contains("href=\"images/0.jpg\""));
+    }
+
+    fn app_illust() -> serde_json::Value {
+        serde_json::json!({
+            "id": 1,
+            "title": "t",
+            "type": "manga",
+            "user": { "id": 3, "name": "u" },
+            "tags": [{ "name": "オリジナル", "translated_name": "original" }],
+            "x_restrict": 0,
+            "illust_ai_type": 1,
+            "page_count": 2,
+            "width": 1000,
+            "height": 3000,
+            "meta_single_page": {},
+            "meta_pages": [
+                { "image_urls": { "square_medium": "https://i.pximg.net/1_p0_sq.jpg", "original": "https://i.pximg.net/1_p0.png" } },
+                { "image_urls": { "square_medium": "https://i.pximg.net/1_p1_sq.jpg", "original": "https://i.pximg.net/1_p1.png" } },
+            ],
+        })
+    }
+
+    /// Stand-in of the OAuth endpoint and the app api, which accepts only the latest access token
+    /// and records refresh tokens posted
+    async fn app_stand_in(expires_in: u64) -> (url::Url, Arc<Mutex<Vec<String>>>) {
+        let posted = Arc::new(Mutex::new(Vec::<String>::new()));
+        let recorded = posted.clone();
+        let base = crate::stand_in::serve(move |req| {
+            let body = match req.path.as_str() {
+                "/auth/token" => {
+                    let form = url::form_urlencoded::parse(&req.body)
+                        .into_owned()
+                        .collect::<HashMap<_, _>>();
+                    assert_eq!(form["grant_type"], "refresh_token");
+                    let refresh = form["refresh_token"].clone();
+                    let mut posted = posted.lock().unwrap();
+                    posted.push(refresh.clone());
+                    if refresh == "bad" {
+                        serde_json::json!({ "has_error": true, "errors": { "system": { "message": "Invalid refresh token" } } })
+                    } else {
+                        let n = posted.len();
+                        serde_json::json!({
+                            "access_token": format!("a{}", n),
+                            "refresh_token": format!("r{}", n),
+                            "expires_in": expires_in,
+                        })
+                    }
+                }
+                path if path.starts_with("/v1/illust/detail?illust_id=1") => {
+                    let latest = format!("bearer a{}", posted.lock().unwrap().len());
+                    if req.header("authorization") == Some(latest.as_str()) {
+                        serde_json::json!({ "illust": app_illust() })
+                    } else {
+                        serde_json::json!({ "error": { "message": "Error occurred at the OAuth process." } })
+                    }
+                }
+                path if path.starts_with("/v1/user/detail?user_id=3") => {
+                    serde_json::json!({ "user": { "id": 3, "name": "u" } })
+                }
+                path if path.starts_with("/v1/user/bookmarks/illust?user_id=3") => {
+                    serde_json::json!({
+                        "illusts": [
+                            app_illust(),
+                            // deleted works are listed without images
+                            { "id": 4, "title": "", "type": "illust", "meta_single_page": {}, "meta_pages": [], "image_urls": {} },
+                        ],
+                        "next_url": null,
+                    })
+                }
+                path if path.starts_with("/v1/illust/detail?illust_id=5") => {
+                    serde_json::json!({ "illust": {
+                        "id": 5,
+                        "title": "u",
+                        "type": "ugoira",
+                        "user": { "id": 3, "name": "u" },
+                        "page_count": 1,
+                    } })
+                }
+                path if path.starts_with("/v1/ugoira/metadata?illust_id=5") => {
+                    serde_json::json!({ "ugoira_metadata": {
+                        "zip_urls": { "medium": "https://i.pximg.net/img-zip-ugoira/img/5_ugoira600x600.zip" },
+                        "frames": [{ "file": "000000.png", "delay": 50 }],
+                    } })
+                }
+                path if path.starts_with("/v1/illust/detail?illust_id=2") => {
+                    serde_json::json!({ "error": {
+                        "user_message": "該当作品は削除されたか、存在しない作品IDです。",
+                        "message": "",
+                        "reason": "",
+                    } })
+                }
+                _ => serde_json::json!({}),
+            };
+            Some(body.to_string().into_bytes())
+        })
+        .await;
+        (format!("{}/", base).parse().unwrap(), recorded)
+    }
+
+    #[tokio::test]
+    async fn app_oauth() {
+        let client = finata::utils::Client::new();
+        let (api, posted) = app_stand_in(3600).await;
+        let token_url = api.join("auth/token").unwrap();
+        let auth = Auth::new("r0").token_url(token_url.clone());
+        assert_eq!(auth.access_token(&client).await.unwrap(), "a1");
+        assert_eq!(auth.access_token(&client).await.unwrap(), "a1");
+        assert_eq!(*posted.lock().unwrap(), ["r0"]);
+        // rotated refresh tokens are kept
+        assert_eq!(auth.refresh_token(), "r1");
+
+        let mut extractor = AppPixiv::new(auth.clone(), "1".to_owned()).api(api.clone());
+        let res = extractor.extract().await.unwrap();
+        assert_eq!(res.raws()[0].tracks.len(), 2);
+        assert_eq!(res.raws()[0].meta["tags"][0]["translation"], "original");
+        assert_eq!(res.raws()[0].meta["ai_generated"], false);
+        assert_eq!(res.raws()[0].meta["like_count"], serde_json::Value::Null);
+        assert_eq!(
+            res.raws()[0].meta["images"],
+            serde_json::json!([
+                { "size": "original", "width": 1000, "height": 3000 },
+                { "size": "original", "width": null, "height": null },
+            ])
+        );
+        // the medium zip is kept when no larger one is provided
+        let mut extractor = AppPixiv::new(auth.clone(), "5".to_owned()).api(api.clone());
+        let res = extractor.extract().await.unwrap();
+        assert!(res.raws()[0].tracks[0]
+            .as_url()
+            .path()
+            .ends_with("5_ugoira600x600.zip"));
+        assert_eq!(res.raws()[0].meta["ugoira"]["size"], "600x600");
+        assert_eq!(res.raws()[0].meta["ugoira"]["mime_type"], "image/png");
+        // errors other than rejected tokens do not refresh them
+        let mut extractor = AppPixiv::new(auth.clone(), "2".to_owned()).api(api.clone());
+        assert!(matches!(
+            extractor.extract().await,
+            Err(finata::Error::InvalidResponse { .. })
+        ));
+        assert_eq!(*posted.lock().unwrap(), ["r0"]);
+        // another session revokes the access token, which is refreshed once on rejection
+        let other = Auth::new("r1").token_url(token_url.clone());
+        other.refresh(&client).await.unwrap();
+        let mut extractor = AppPixiv::new(auth.clone(), "1".to_owned())
+            .api(api.clone())
+            .size(Size::ThumbMini);
+        let res = extractor.extract().await.unwrap();
+        assert!(res.raws()[0].tracks[1].as_url().path().ends_with("_sq.jpg"));
+        assert_eq!(res.raws()[0].meta["images"][0]["width"], 360);
+        assert_eq!(*posted.lock().unwrap(), ["r0", "r1", "r1"]);
+        assert_eq!(auth.refresh_token(), "r3");
+
+        let bad = Auth::new("bad").token_url(token_url);
+        assert!(matches!(
+            bad.access_token(&client).await,
+            Err(finata::Error::LoginRequired { .. })
+        ));
+
+        // tokens expiring within a minute are refreshed before use
+        let (api, posted) = app_stand_in(30).await;
+        let auth = Auth::new("r0").token_url(api.join("auth/token").unwrap());
+        assert_eq!(auth.access_token(&client).await.unwrap(), "a1");
+        assert_eq!(auth.access_token(&client).await.unwrap(), "a2");
+        assert_eq!(*posted.lock().unwrap(), ["r0", "r1"]);
+    }
+
+    #[tokio::test]
+    async fn app_bookmarks() {
+        let (api, _) = app_stand_in(3600).await;
+        let auth = Auth::new("r0").token_url(api.join("auth/token").unwrap());
+        let res = AppBookmarks::new(auth, "3".to_owned())
+            .api(api)
+            .extract()
+            .await
+            .unwrap();
+        assert_eq!(res.title(), "u");
+        assert_eq!(res.raws().len(), 2);
+        assert_eq!(res.raws()[0].tracks.len(), 2);
+        assert!(res.raws()[1].tracks.is_empty());
+        assert_eq!(res.raws()[1].meta["unavailable"], true);
+        assert_eq!(res.raws()[1].meta["pid"], "4");
+    }
+
+    #[test]
+    fn ugoira_meta() {
+        let body = serde_json::json!({
+            "src": "https://i.pximg.net/img-zip-ugoira/img/2020/01/01/00/00/00/1_ugoira600x600.zip",
+            "originalSrc": "https://i.pximg.net/img-zip-ugoira/img/2020/01/01/00/00/00/1_ugoira1920x1080.zip",
+            "mime_type": "image/jpeg",
+            "frames": [
+                { "file": "000000.jpg", "delay": 100 },
+                { "file": "000001.jpg", "delay": 60 },
+            ],
+        });
+        let meta = ugoira::UgoiraMeta::from_json(&body).unwrap();
+        assert!(meta.src.as_str().ends_with("1_ugoira1920x1080.zip"));
+        assert_eq!(meta.frames.len(), 2);
+        assert_eq!(meta.frames[1].delay, 60);
+        assert_eq!(meta.to_json()["frames"][0]["file"], "000000.jpg");
+        assert!(ugoira::UgoiraMeta::from_json(&serde_json::json!({})).is_err());
+    }
+
+    #[cfg(feature = "ugoira")]
+    #[test]
+    fn ugoira_render() {
+        use std::io::{Cursor, Write};
+        use ugoira::{render, Format, Frame};
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let mut frames = Vec::new();
+        for (i, color) in [[255, 0, 0], [0, 0, 255]].into_iter().enumerate() {
+            let file = format!("{:06}.png", i);
+            let mut png = Vec::new();
+            image::RgbImage::from_pixel(4, 3, image::Rgb(color))
+                .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+                .unwrap();
+            zip.start_file(file.as_str(), zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(&png).unwrap();
+            frames.push(Frame { file, delay: 80 });
+        }
+        let zip = zip.finish().unwrap().into_inner();
+
+        let gif = render(&zip, &frames, Format::Gif).unwrap();
+        assert!(gif.starts_with(b"GIF89a"));
+        let apng = render(&zip, &frames, Format::Apng).unwrap();
+        assert!(apng.starts_with(b"\x89PNG"));
+        assert!(apng.windows(4).any(|w| w == b"acTL"));
+        let webp = render(&zip, &frames, Format::WebP).unwrap();
+        assert_eq!(&webp[..4], b"RIFF");
+        assert_eq!(&webp[8..16], b"WEBPVP8X");
+        assert_eq!(webp.windows(4).filter(|w| w == b"ANMF").count(), 2);
+        let decoded = image::load_from_memory(&webp).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (4, 3));
+
+        frames[1].file = "missing.png".to_owned();
+        assert!(render(&zip, &frames, Format::Gif).is_err());
+    }
+}
+
+#[cfg(test)]
+mod recorder {
+    use finata::{
+        recorder::{Recorder, Split},
+        utils::Client,
+        AsClient, Error, Extract, Origin, Playlist,
+    };
+    use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+    /// Serves `routes` over http, closing connections after each response
+    async fn serve(routes: HashMap<&'static str, Vec<u8>>) -> String {
+        crate::stand_in::serve(move |req| routes.get(req.path.as_str()).cloned()).await
+    }
+
+    /// Extracts `url` for `times` times, then reports the room offline
+    struct StandIn {
+        client: Client,
+        url: String,
+        times: usize,
+    }
+
+    #[async_trait::async_trait]
+    impl Extract for StandIn {
+        async fn extract(&mut self) -> finata::FinaResult {
+            if self.times == 0 {
+                return Err(Error::LiveOffline {
+                    room: "stand-in".to_owned(),
+                });
+            }
+            self.times -= 1;
+            let origin = Origin::video(self.url.parse().unwrap(), String::new());
+            Ok(Playlist::new(vec![origin], String::new()))
+        }
+    }
+
+    impl AsClient for StandIn {
+        fn client(&self) -> &Client {
+            &self.client
+        }
+        fn client_mut(&mut self) -> &mut Client {
+            &mut self.client
+        }
+    }
+
+    fn stand_in(url: String, times: usize) -> StandIn {
+        StandIn {
+            client: Client::new(),
+            url,
+            times,
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("finata-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn flv_tag(kind: u8, timestamp: u32, data: &[u8]) -> Vec<u8> {
+        let len = data.len() as u32;
+        let ts = timestamp.to_be_bytes();
+        let mut tag = vec![kind];
+        tag.extend_from_slice(&len.to_be_bytes()[1..]);
+        tag.extend_from_slice(&[ts[1], ts[2], ts[3], ts[0], 0, 0, 0]);
+        tag.extend_from_slice(data);
+        tag.extend_from_slice(&(len + 11).to_be_bytes());
+        tag
+    }
+
+    fn flv_headers() -> Vec<u8> {
+        let mut headers = b"FLV\x01\x05\x00\x00\x00\x09\x00\x00\x00\x00".to_vec();
+        headers.extend(flv_tag(18, 0, b"onMetaData"));
+        headers.extend(flv_tag(9, 0, &[0x17, 0, 0, 0, 0, 1]));
+        headers.extend(flv_tag(8, 0, &[0xaf, 0, 0x12, 0x10]));
+        headers
+    }
+
+    #[tokio::test]
+    async fn flv_split_and_reconnect() {
+        let keyframe = |ts| flv_tag(9, ts, &[[0x17, 1].as_slice(), &[0xaa; 100]].concat());
+        let interframe = |ts| flv_tag(9, ts, &[[0x27, 1].as_slice(), &[0xbb; 100]].concat());
+        let mut stream = flv_headers();
+        stream.extend(keyframe(0));
+        stream.extend(interframe(40));
+        stream.extend(keyframe(1000));
+        stream.extend(interframe(1040));
+        let base = serve(HashMap::from([("/live.flv", stream)])).await;
+        let dir = temp_dir("flv");
+        let mut recorder = Recorder::new(stand_in(format!("{}/live.flv", base), 2), &dir)
+            .split(Split::size(200))
+            .retry(0, Duration::ZERO);
+        let files = recorder.record().await.unwrap();
+        // split once per connection, and a new file for the second connection
+        assert_eq!(files.len(), 4);
+        for (i, file) in files.iter().enumerate() {
+            let data = std::fs::read(file).unwrap();
+            assert!(data.starts_with(&flv_headers()));
+            let rest = &data[flv_headers().len()..];
+            let expected = match i % 2 {
+                0 => [keyframe(0), interframe(40)].concat(),
+                _ => [keyframe(1000), interframe(1040)].concat(),
+            };
+            assert_eq!(rest, expected.as_slice());
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn hls_split() {
+        let playlist = "#EXTM3U\n#EXT-X-TARGETDURATION:1\n#EXT-X-MEDIA-SEQUENCE:7\n\
+            #EXTINF:1.0,\nseg7.ts\n#EXTINF:1.0,\nseg8.ts\n#EXTINF:1.0,\nseg9.ts\n#EXT-X-ENDLIST\n";
+        let base = serve(HashMap::from([
+            ("/hls/live.m3u8", playlist.as_bytes().to_vec()),
+            ("/hls/seg7.ts", b"seven".to_vec()),
+            ("/hls/seg8.ts", b"eight".to_vec()),
+            ("/hls/seg9.ts", b"nine".to_vec()),
+        ]))
+        .await;
+        let dir = temp_dir("hls");
+        let mut recorder = Recorder::new(stand_in(format!("{}/hls/live.m3u8", base), 1), &dir)
+            .split(Split::duration(Duration::from_secs(2)))
+            .retry(0, Duration::ZERO);
+        let files = recorder.record().await.unwrap();
+        assert_eq!(files.len(), 2);
+        assert!(files[0].extension().unwrap() == "ts");
+        assert_eq!(std::fs::read(&files[0]).unwrap(), b"seveneight");
+        assert_eq!(std::fs::read(&files[1]).unwrap(), b"nine");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn offline() {
+        let dir = temp_dir("offline");
+        let mut recorder = Recorder::new(stand_in(String::from("http://127.0.0.1/"), 0), &dir);
+        assert!(recorder.record().await.unwrap().is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn gives_up() {
+        let base = serve(HashMap::new()).await;
+        let dir = temp_dir("gives-up");
+        let mut recorder = Recorder::new(stand_in(format!("{}/gone.flv", base), 10), &dir)
+            .retry(2, Duration::ZERO);
+        assert!(matches!(
+            recorder.record().await,
+            Err(Error::NetworkError { .. })
+        ));
+        assert_eq!(recorder.extractor().times, 7);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_headers_only() {
+        let base = serve(HashMap::from([("/live.flv", flv_headers())])).await;
+        let dir = temp_dir("headers-only");
+        let mut recorder = Recorder::new(stand_in(format!("{}/live.flv", base), 10), &dir)
+            .retry(2, Duration::ZERO);
+        recorder.record().await.unwrap();
+        assert_eq!(recorder.extractor().times, 7);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
+
+/// Minimal http server shared by tests that need a stand-in of a remote api
+#[cfg(test)]
+mod stand_in {
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    pub struct Request {
+        /// path along with the query
+        pub path: String,
+        /// header lines, lowercased
+        pub headers: Vec<String>,
+        pub body: Vec<u8>,
+    }
+
+    impl Request {
+        /// Returns the value of header `name`, which should be lowercase
+        pub fn header(&self, name: &str) -> Option<&str> {
+            self.headers.iter().find_map(|line| {
+                line.strip_prefix(name)
+                    .and_then(|rest| rest.strip_prefix(':'))
+                    .map(str::trim)
+            })
+        }
+    }
+
+    /// Serves responses of `handle` over http, closing connections after each response.
+    /// Requests `handle` returns `None` for are answered with 404
+    pub async fn serve<F>(handle: F) -> String
+    where
+        F: Fn(Request) -> Option<Vec<u8>> + Send + Sync + 'static,
+    {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = Arc::new(handle);
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let handle = handle.clone();
+                tokio::spawn(async move {
+                    let mut req = Vec::new();
+                    let mut buf = [0; 1024];
+                    let head_len = loop {
+                        if let Some(i) = req.windows(4).position(|w| w == b"\r\n\r\n") {
+                            break i + 4;
+                        }
+                        match stream.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => req.extend_from_slice(&buf[..n]),
+                        }
+                    };
+                    let head = String::from_utf8_lossy(&req[..head_len]).into_owned();
+                    let mut lines = head.lines();
+                    let path = lines
+                        .next()
+                        .and_then(|line| line.split(' ').nth(1))
+                        .unwrap_or_default()
+                        .to_owned();
+                    let headers = lines
+                        .filter(|line| !line.is_empty())
+                        .map(str::to_lowercase)
+                        .collect::<Vec<_>>();
+                    let len = headers
+                        .iter()
+                        .find_map(|line| line.strip_prefix("content-length:"))
+                        .and_then(|len| len.trim().parse().ok())
+                        .unwrap_or(0);
+                    while req.len() < head_len + len {
+                        match stream.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => req.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let body = req.split_off(head_len);
+                    let resp = match handle(Request {
+                        path,
+                        headers,
+                        body,
+                    }) {
+                        Some(body) => {
+                            let mut resp = format!(
+                                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                                body.len()
+                            )
+                            .into_bytes();
+                            resp.extend_from_slice(&body);
+                            resp
+                        }
+                        None => b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_vec(),
+                    };
+                    let _ = stream.write_all(&resp).await;
+                });
+            }
+        });
+        format!("http://{}", addr)
+    }
 }